@@ -3,4 +3,6 @@ use wesl::ModulePath;
 fn main() {
     wesl::Wesl::new("src/shaders").build_artifact(&ModulePath::from_path("/vert.wesl"), "vertex_shader");
     wesl::Wesl::new("src/shaders").build_artifact(&ModulePath::from_path("/frag.wesl"), "fragment_shader");
-}
\ No newline at end of file
+    wesl::Wesl::new("src/shaders").build_artifact(&ModulePath::from_path("/text.wesl"), "text_shader");
+    wesl::Wesl::new("src/shaders").build_artifact(&ModulePath::from_path("/fxaa.wesl"), "fxaa_shader");
+}
@@ -1,70 +1,1265 @@
-use crate::renderer::Renderer;
+use crate::camera::{Camera, Keyframe, Projection, Track};
+use crate::config::{Config, VideoModeRequest};
+#[cfg(feature = "gamepad")]
+use crate::gamepad::Gamepads;
+use crate::input::Action;
+use crate::renderer::{DebugView, Renderer, RendererConfig};
+use crate::scene::{Material, Plane, PointLight, Primitive, Scene, SceneManager, Sphere};
+use crate::text::TextRenderer;
 
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use glam::{Vec2, Vec3};
+use serde::{Deserialize, Serialize};
 use winit::application::ApplicationHandler;
-use winit::event::{StartCause, WindowEvent};
-use winit::event_loop::{EventLoop, ActiveEventLoop};
-use winit::window::{Window, WindowId};
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, StartCause, WindowEvent};
+use winit::event_loop::{EventLoop, EventLoopProxy, ActiveEventLoop};
+use winit::keyboard::{ModifiersState, PhysicalKey};
+use winit::monitor::{MonitorHandle, VideoModeHandle};
+use winit::window::{Fullscreen, Window, WindowId, WindowLevel};
 
 use anyhow::Context;
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrbitActivation {
+    #[default]
+    Hold,
+    Toggle,
+}
+
+// resolves once the renderer is created and handed back to the event loop
+pub type RendererFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+pub enum AppEvent {
+    RendererReady(Box<anyhow::Result<Renderer>>),
+}
+
+struct Benchmark {
+    duration: Duration,
+    start: Option<Instant>,
+    presented_frames: u32,
+}
+
+// PNG screenshots at a fixed resolution, rendered in tiles independent of the window size
+struct TiledCapture {
+    width: u32,
+    height: u32,
+    tile_size: u32,
+}
+
+struct TrackRecording {
+    track: Track,
+    start: Instant,
+    path: PathBuf,
+}
+
+struct TrackPlayback {
+    track: Track,
+    time: f32,
+    looping: bool,
+    // every tick is saved here as a numbered PNG when set
+    capture_dir: Option<PathBuf>,
+    captured_frames: u32,
+}
+
 pub struct App {
     renderer: Option<Renderer>,
+    renderer_config: RendererConfig,
+    config: Config,
+    camera: Camera,
+    initial_camera: Camera,
+    orbiting: bool,
+    cursor_position: Option<PhysicalPosition<f64>>,
+    modifiers: ModifiersState,
+    turntable: bool,
+    last_frame_time: Option<Instant>,
+    tick_accumulator: f32,
+    frame_index: u64,
+    paused: bool,
+    step_requested: bool,
+    section_offset: Option<f32>,
+    // field of view the perspective camera eases toward, in radians
+    fov_target: Option<f32>,
+    // index into the cameras of the current scene, unused when it defines none
+    camera_index: usize,
+    // last primitive hit by a pick, the one the solo view isolates
+    picked: Option<Primitive>,
+    hud: bool,
+    // command line override of the config monitor, kept out of the saved config
+    monitor: Option<usize>,
+    start_fullscreen: bool,
+    // exponentially smoothed time between redraws in seconds
+    frame_interval: f32,
+    skybox: Option<[PathBuf; 6]>,
+    scene: Option<Scene>,
+    scenes: Option<SceneManager>,
+    benchmark: Option<Benchmark>,
+    track_playback: Option<TrackPlayback>,
+    track_recording: Option<TrackRecording>,
+    // where a recording is saved, a new timestamped file when unset
+    record_path: Option<PathBuf>,
+    overwrite_record: bool,
+    exit_screenshot: Option<PathBuf>,
+    tiled_capture: Option<TiledCapture>,
+    // set once a frame was presented, an exit screenshot is skipped before that
+    frame_rendered: bool,
+    // render a single frame to stdout as binary PPM from a hidden window, then exit
+    ppm_output: bool,
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<Gamepads>,
+    #[cfg(feature = "gamepad")]
+    last_gamepad_poll: Option<Instant>,
+    proxy: Option<EventLoopProxy<AppEvent>>,
+    spawn: Option<Box<dyn Fn(RendererFuture)>>,
     result: anyhow::Result<()>,
 }
 
-impl Default for App {
-    fn default() -> Self {
+impl App
+where
+    Self: ApplicationHandler<AppEvent>,
+{
+    const TITLE: &str = "RT";
+    const ORBIT_SENSITIVITY: f32 = 0.005;
+    const ZOOM_STEP: f32 = 0.9;
+    const MOUSE_SENSITIVITY_STEP: f32 = 1.25;
+    const MIN_MOUSE_SENSITIVITY: f32 = 0.05;
+    const MAX_MOUSE_SENSITIVITY: f32 = 20.0;
+    const FOV_STEP: f32 = 0.8;
+    const MIN_FOV_DEGREES: f32 = 10.0;
+    const MAX_FOV_DEGREES: f32 = 120.0;
+    // time constant of the exponential easing in seconds, applied per elapsed time so it does not depend on the frame rate
+    const FOV_SMOOTHING: f32 = 0.15;
+    const FOV_SNAP: f32 = 1e-4;
+    // seconds of wall clock time between recorded camera poses
+    const RECORD_INTERVAL: f32 = 0.1;
+    // full stick deflection, in radians per second and view distances per second
+    #[cfg(feature = "gamepad")]
+    const GAMEPAD_LOOK_SPEED: f32 = 2.0;
+    #[cfg(feature = "gamepad")]
+    const GAMEPAD_MOVE_SPEED: f32 = 0.5;
+    // gilrs has no wakeup of its own, so the event loop polls at these intervals
+    #[cfg(feature = "gamepad")]
+    const GAMEPAD_ACTIVE_INTERVAL: Duration = Duration::from_millis(16);
+    #[cfg(feature = "gamepad")]
+    const GAMEPAD_IDLE_INTERVAL: Duration = Duration::from_millis(100);
+    const TICK: f32 = 1.0 / 60.0;
+    // drop simulation time beyond this after a stall instead of fast forwarding
+    const MAX_CATCH_UP: f32 = 0.25;
+    const SECTION_STEP: f32 = 0.1;
+    const MAX_ANISOTROPY: u16 = 16;
+    const HUD_SCALE: f32 = 3.0;
+    // average adult IPD in meters, assuming scenes are modeled in meters
+    const DEFAULT_STEREO_IPD: f32 = 0.064;
+
+    //public
+
+    pub fn new(config: Config, renderer_config: RendererConfig) -> Self {
         Self {
             renderer: None,
+            renderer_config,
+            config,
+            camera: Camera::default(),
+            initial_camera: Camera::default(),
+            orbiting: false,
+            cursor_position: None,
+            modifiers: ModifiersState::empty(),
+            turntable: false,
+            last_frame_time: None,
+            tick_accumulator: 0.0,
+            frame_index: 0,
+            paused: false,
+            step_requested: false,
+            section_offset: None,
+            fov_target: None,
+            camera_index: 0,
+            picked: None,
+            hud: false,
+            monitor: None,
+            start_fullscreen: false,
+            frame_interval: 0.0,
+            skybox: None,
+            scene: None,
+            scenes: None,
+            benchmark: None,
+            track_playback: None,
+            track_recording: None,
+            record_path: None,
+            overwrite_record: false,
+            exit_screenshot: None,
+            tiled_capture: None,
+            frame_rendered: false,
+            ppm_output: false,
+            #[cfg(feature = "gamepad")]
+            gamepads: Gamepads::new().inspect_err(|e| log::warn!("Gamepads disabled: {:?}", e)).ok(),
+            #[cfg(feature = "gamepad")]
+            last_gamepad_poll: None,
+            proxy: None,
+            spawn: None,
             result: Ok(()),
         }
     }
-}
 
-impl App
-where
-    Self: ApplicationHandler,
-{
-    //public
+    pub fn with_skybox(mut self, faces: [PathBuf; 6]) -> Self {
+        self.skybox = Some(faces);
+        self
+    }
+
+    pub fn with_scene(mut self, scene: Scene) -> Self {
+        if let Some(camera) = scene.camera() {
+            self.camera = *camera;
+        }
+        self.camera_index = 0;
+        self.scene = Some(scene);
+        self
+    }
+
+    // the scene currently shown must already be passed to with_scene
+    pub fn with_scene_manager(mut self, scenes: SceneManager) -> Self {
+        self.scenes = Some(scenes);
+        self
+    }
 
-   pub fn run(mut self) -> anyhow::Result<()> {
-        let event_loop = EventLoop::new().context("Failed to create event loop")?;
+    pub fn with_fullscreen_monitor(mut self, index: usize) -> Self {
+        self.monitor = Some(index);
+        self.start_fullscreen = true;
+        self
+    }
+
+    pub fn with_benchmark(mut self, duration: Duration) -> Self {
+        self.renderer_config.present_mode = wgpu::PresentMode::Immediate;
+        self.benchmark = Some(Benchmark {
+            duration,
+            start: None,
+            presented_frames: 0,
+        });
+        self
+    }
+
+    pub fn with_camera_track(mut self, track: Track, looping: bool) -> Self {
+        self.track_playback = Some(TrackPlayback {
+            track,
+            time: 0.0,
+            looping,
+            capture_dir: None,
+            captured_frames: 0,
+        });
+        self
+    }
+
+    // needs with_camera_track first, the directory must exist
+    pub fn with_track_capture(mut self, dir: PathBuf) -> Self {
+        if let Some(playback) = self.track_playback.as_mut() {
+            playback.capture_dir = Some(dir);
+        }
+        self
+    }
+
+    pub fn with_track_recording(mut self, path: PathBuf, overwrite: bool) -> Self {
+        self.record_path = Some(path);
+        self.overwrite_record = overwrite;
+        self
+    }
+
+    // the extension picks the format like for interactive screenshots, .exr saves linear radiance
+    pub fn with_screenshot_on_exit(mut self, path: PathBuf) -> Self {
+        self.exit_screenshot = Some(path);
+        self
+    }
+
+    pub fn with_tiled_capture(mut self, width: u32, height: u32, tile_size: u32) -> Self {
+        self.tiled_capture = Some(TiledCapture {
+            width,
+            height,
+            tile_size,
+        });
+        self
+    }
+
+    pub fn with_ppm_output(mut self) -> Self {
+        self.ppm_output = true;
+        self
+    }
+
+    // native only: blocks on the renderer initialization inside the event loop
+    pub fn run(self) -> anyhow::Result<()> {
+        self.run_async(|future| pollster::block_on(future))
+    }
+
+    // `spawn` drives the renderer initialization without blocking the event loop,
+    // the renderer arrives later as an AppEvent. On wasm the browser thread must
+    // never block, pass wasm_bindgen_futures::spawn_local there. Async runtimes
+    // need a local (non Send) spawner since the surface is tied to the window thread.
+    pub fn run_async(mut self, spawn: impl Fn(RendererFuture) + 'static) -> anyhow::Result<()> {
+        let event_loop = EventLoop::with_user_event().build().context("Failed to create event loop")?;
 
         event_loop.set_control_flow(winit::event_loop::ControlFlow::Wait);
 
+        self.proxy = Some(event_loop.create_proxy());
+        self.spawn = Some(Box::new(spawn));
+
         event_loop.run_app(&mut self).context("Application run failed")?;
         self.result
     }
 
     //private
 
-    fn create_window(&mut self, event_loop: &ActiveEventLoop) -> anyhow::Result<Window> {
-        let window_attributes = Window::default_attributes().with_title("RT");
+    fn create_window(&self, event_loop: &ActiveEventLoop) -> anyhow::Result<Window> {
+        let window_attributes = Window::default_attributes()
+            .with_title(Self::TITLE)
+            .with_visible(!self.ppm_output)
+            .with_window_level(Self::window_level(self.config.always_on_top))
+            .with_decorations(!self.config.hide_decorations)
+            .with_fullscreen(self.start_fullscreen.then(|| self.fullscreen(event_loop.available_monitors(), event_loop.primary_monitor())));
         let window = event_loop.create_window(window_attributes).context("Failed to create window")?;
         log::info!("Window created");
         Ok(window)
     }
 
+    // the configured monitor, `current` when there is none, exclusive if a video mode is configured
+    fn fullscreen(&self, mut monitors: impl Iterator<Item = MonitorHandle>, current: Option<MonitorHandle>) -> Fullscreen {
+        let monitor = self.monitor.or(self.config.monitor).and_then(|index| {
+            let monitor = monitors.nth(index);
+            if monitor.is_none() {
+                log::warn!("Monitor {} does not exist, going fullscreen on the current monitor", index);
+            }
+            monitor
+        });
+        match &monitor {
+            Some(monitor) => log::info!("Fullscreen on monitor {} ({}x{})", monitor.name().unwrap_or_else(|| "unknown".into()), monitor.size().width, monitor.size().height),
+            None => log::info!("Fullscreen on the current monitor"),
+        }
+
+        if let Some(request) = self.config.video_mode {
+            match monitor.clone().or(current).and_then(|monitor| Self::closest_video_mode(&monitor, request)) {
+                Some(mode) => {
+                    log::info!("Exclusive video mode {}x{} at {:.2} Hz", mode.size().width, mode.size().height, mode.refresh_rate_millihertz() as f64 / 1000.0);
+                    return Fullscreen::Exclusive(mode);
+                }
+                None => log::warn!("No video mode available, falling back to borderless fullscreen"),
+            }
+        }
+        Fullscreen::Borderless(monitor)
+    }
+
+    fn closest_video_mode(monitor: &MonitorHandle, request: VideoModeRequest) -> Option<VideoModeHandle> {
+        let refresh_rate_millihertz = request.refresh_rate_hz.map(|hz| hz * 1000);
+        monitor.video_modes().min_by_key(|mode| {
+            let size_difference = mode.size().width.abs_diff(request.width) + mode.size().height.abs_diff(request.height);
+            let refresh_difference = refresh_rate_millihertz.map_or(0, |millihertz| mode.refresh_rate_millihertz().abs_diff(millihertz));
+            // without a requested rate prefer the fastest one, then the deepest color
+            let refresh_preference = if refresh_rate_millihertz.is_some() { 0 } else { u32::MAX - mode.refresh_rate_millihertz() };
+            (size_difference, refresh_difference, refresh_preference, u16::MAX - mode.bit_depth())
+        })
+    }
+
+    // every title change goes through here so the title reflects the whole app state
+    fn update_title(&self) {
+        let Some(renderer) = self.renderer.as_ref() else {
+            return;
+        };
+        let mut title = Self::TITLE.to_string();
+        if let Some(name) = self.scenes.as_ref().and_then(|scenes| scenes.current_name()) {
+            title += &format!(" - {}", name);
+        }
+        if let Some(scene_camera) = self.scene.as_ref().and_then(|scene| scene.cameras().get(self.camera_index)) {
+            title += &format!(" - {}", scene_camera.name);
+        }
+        if renderer.debug_view() != DebugView::Shaded {
+            title += &format!(" [{:?}]", renderer.debug_view());
+        }
+        if let Some(primitive) = self.solo_primitive() {
+            title += &format!(" [Solo {:?}]", primitive);
+        }
+        renderer.set_title(&title);
+    }
+
+    fn window_level(always_on_top: bool) -> WindowLevel {
+        if always_on_top { WindowLevel::AlwaysOnTop } else { WindowLevel::Normal }
+    }
+
     fn init_renderer(&mut self, event_loop: &ActiveEventLoop) -> anyhow::Result<()> {
         let window = self.create_window(event_loop)?;
-        let renderer = pollster::block_on(Renderer::new(window))?;
+        let scene = match self.scene.take() {
+            Some(scene) => scene,
+            None => Self::create_scene()?,
+        };
+        // kept for picking, the renderer only holds the GPU copy
+        self.scene = Some(scene.clone());
+        self.initial_camera = self.camera;
+
+        let proxy = self.proxy.clone().context("Renderer initialized outside of run")?;
+        let spawn = self.spawn.as_ref().context("Renderer initialized outside of run")?;
+        let camera = self.camera;
+        let renderer_config = self.renderer_config.clone();
+        spawn(Box::pin(async move {
+            let renderer = Renderer::new(window, &scene, &camera, &renderer_config).await;
+            // only fails once the event loop is gone, nobody is left to receive it
+            let _ = proxy.send_event(AppEvent::RendererReady(Box::new(renderer)));
+        }));
+        Ok(())
+    }
+
+    fn finish_renderer(&mut self, renderer: anyhow::Result<Renderer>) -> anyhow::Result<()> {
+        let mut renderer = renderer?;
+        if let Some(sky) = self.config.sky {
+            renderer.set_sky_gradient(sky.top.into(), sky.bottom.into());
+        }
+        if let Some(grid) = self.config.grid {
+            renderer.set_grid_style(grid.spacing, grid.color.into());
+        }
+        if let Some(faces) = &self.skybox {
+            renderer.set_cubemap(faces.each_ref().map(|path| path.as_path()))?;
+        }
+        let adapter_info = renderer.adapter_info();
+        log::info!("Renderer initialized on {} ({:?} backend)", adapter_info.name, renderer.backend());
+        log::debug!("Adapter features: {:?}", renderer.adapter_features());
+        log::info!("Surface output: {:?}", renderer.surface_output());
+        log::info!("Tone mapper: {:?}", renderer.tone_mapper());
+        // the camera may have moved while the renderer was being created
+        renderer.set_camera(&self.camera);
         self.renderer = Some(renderer);
-        log::info!("Renderer initialized");
+        self.update_title();
         Ok(())
     }
+
+    fn create_scene() -> anyhow::Result<Scene> {
+        let mut scene = Scene::default();
+        let checker = scene.add_material(Material::new([1.0, 1.0, 1.0], true));
+        let white = scene.add_material(Material::new([0.8, 0.8, 0.8], false));
+        scene.add_plane(Plane::new([0.0, 1.0, 0.0], 0.0, checker))?;
+        scene.add_sphere(Sphere::new([0.0, 1.0, 0.0], 1.0, white))?;
+        scene.add_point_light(PointLight::new([-2.0, 1.0, 0.0], [4.0, 0.2, 0.2], 6.0))?;
+        scene.add_point_light(PointLight::new([0.0, 1.0, -1.5], [0.2, 4.0, 0.2], 6.0))?;
+        scene.add_point_light(PointLight::new([2.0, 1.0, 0.0], [0.2, 0.2, 4.0], 6.0))?;
+        Ok(scene)
+    }
+
+    fn switch_scene(&mut self, forward: bool) {
+        let Some(scenes) = self.scenes.as_mut() else {
+            return;
+        };
+        let Some(scene) = (if forward { scenes.next() } else { scenes.previous() }) else {
+            log::error!("No scene in the directory could be loaded");
+            return;
+        };
+        if let Some(path) = scenes.current_path() {
+            log::info!("Switched to scene {}", path.display());
+        }
+        if let Some(camera) = scene.camera() {
+            self.camera = *camera;
+        }
+        self.camera_index = 0;
+        self.fov_target = None;
+        self.picked = None;
+        self.initial_camera = self.camera;
+        if let Some(renderer) = self.renderer.as_mut() {
+            if let Err(e) = renderer.set_scene(&scene) {
+                log::error!("Failed to upload scene: {:?}", e);
+            }
+            log::debug!("GPU memory after scene switch: {:?}", renderer.memory_report());
+        }
+        self.scene = Some(scene);
+        self.update_camera();
+        self.update_title();
+    }
+
+    // the selected camera also becomes the one reset_camera returns to
+    fn cycle_scene_camera(&mut self, forward: bool) {
+        let Some(scene) = self.scene.as_ref() else {
+            return;
+        };
+        let count = scene.cameras().len();
+        if count == 0 {
+            log::info!("The scene defines no cameras");
+            return;
+        }
+        self.camera_index = if forward { (self.camera_index + 1) % count } else { (self.camera_index + count - 1) % count };
+        let scene_camera = &scene.cameras()[self.camera_index];
+        log::info!("Camera: {}", scene_camera.name);
+        self.camera = scene_camera.camera;
+        self.initial_camera = self.camera;
+        self.orbiting = false;
+        self.fov_target = None;
+        self.update_camera();
+        self.update_title();
+    }
+
+    fn update_camera(&mut self) {
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.set_camera(&self.camera);
+        }
+    }
+
+    fn handle_orbit_button(&mut self, state: ElementState) {
+        self.orbiting = match self.config.orbit_activation {
+            OrbitActivation::Hold => state.is_pressed(),
+            OrbitActivation::Toggle if state.is_pressed() => !self.orbiting,
+            OrbitActivation::Toggle => self.orbiting,
+        };
+    }
+
+    fn handle_cursor_moved(&mut self, position: PhysicalPosition<f64>) {
+        if let Some(last_position) = self.cursor_position.replace(position) && self.orbiting {
+            let delta_x = (position.x - last_position.x) as f32;
+            let delta_y = (position.y - last_position.y) as f32;
+            let sensitivity = Self::ORBIT_SENSITIVITY * self.config.mouse_sensitivity;
+            let pitch_sign = if self.config.invert_y { -1.0 } else { 1.0 };
+            self.camera.orbit(-delta_x * sensitivity, pitch_sign * delta_y * sensitivity);
+            self.update_camera();
+        }
+    }
+
+    fn pick(&mut self) {
+        let (Some(renderer), Some(scene), Some(cursor)) = (self.renderer.as_ref(), self.scene.as_ref(), self.cursor_position) else {
+            return;
+        };
+        let size = renderer.window().inner_size();
+        let mut pixel = Vec2::new(cursor.x as f32, cursor.y as f32);
+        // side by side stereo, each half of the window is the view of one eye
+        let (origin, direction) = match renderer.stereo_ipd() {
+            Some(ipd) => {
+                let eye_width = size.width / 2;
+                let eye_offset = if pixel.x < eye_width as f32 {
+                    -0.5 * ipd
+                } else {
+                    pixel.x -= eye_width as f32;
+                    0.5 * ipd
+                };
+                self.camera.eye_ray(pixel, eye_width, size.height, eye_offset)
+            }
+            None => self.camera.primary_ray(pixel, size.width, size.height),
+        };
+        let hit = scene.intersect_solo(origin, direction, self.solo_primitive());
+        match hit {
+            Some(hit) => log::info!("Picked {:?} at {:.3} (distance {:.3})", hit.primitive, hit.position, hit.distance),
+            None => log::info!("Picked nothing"),
+        }
+        // cross check against the object id target, which also honors clip planes
+        if log::log_enabled!(log::Level::Debug) {
+            match renderer.object_at([cursor.x as u32, cursor.y as u32]) {
+                Ok(primitive) => log::debug!("Object id target: {:?}", primitive),
+                Err(e) => log::warn!("Failed to read the object id target: {:?}", e),
+            }
+        }
+        self.picked = hit.map(|hit| hit.primitive);
+    }
+
+    fn solo_primitive(&self) -> Option<Primitive> {
+        let (Some(renderer), Some(scene)) = (self.renderer.as_ref(), self.scene.as_ref()) else {
+            return None;
+        };
+        Primitive::from_object_id(renderer.solo_object()?, scene.spheres().len())
+    }
+
+    // isolates the last picked primitive, frame also moves the camera onto it
+    fn toggle_solo(&mut self, frame: bool) {
+        let (Some(renderer), Some(scene)) = (self.renderer.as_mut(), self.scene.as_ref()) else {
+            return;
+        };
+        if renderer.solo_object().is_some() {
+            renderer.solo(None);
+            log::info!("Solo view disabled");
+        } else {
+            let Some(primitive) = self.picked else {
+                log::info!("Pick an object with the right mouse button to solo it");
+                return;
+            };
+            renderer.solo(Some(primitive.object_id(scene.spheres().len())));
+            log::info!("Solo view on {:?}", primitive);
+            if frame {
+                match primitive {
+                    Primitive::Sphere(index) => {
+                        let sphere = scene.spheres()[index];
+                        self.camera.frame(sphere.center.into(), sphere.radius);
+                        self.fov_target = None;
+                        self.update_camera();
+                    }
+                    Primitive::Plane(_) => log::info!("Planes are unbounded, the camera is left as is"),
+                }
+            }
+        }
+        self.update_title();
+    }
+
+    #[cfg(feature = "gamepad")]
+    fn update_gamepads(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(gamepads) = self.gamepads.as_mut() else {
+            return;
+        };
+        let state = gamepads.poll();
+        let now = Instant::now();
+        let elapsed = self.last_gamepad_poll.replace(now).map_or(0.0, |last| (now - last).as_secs_f32()).min(Self::MAX_CATCH_UP);
+        let interval = if state.is_active() {
+            // same look settings as the mouse so both devices agree
+            let look = state.look_stick * Self::GAMEPAD_LOOK_SPEED * self.config.mouse_sensitivity * state.speed * elapsed;
+            let pitch_sign = if self.config.invert_y { -1.0 } else { 1.0 };
+            self.camera.orbit(-look.x, -pitch_sign * look.y);
+            self.camera.pan(state.move_stick * Self::GAMEPAD_MOVE_SPEED * self.camera.distance * state.speed * elapsed);
+            self.update_camera();
+            Self::GAMEPAD_ACTIVE_INTERVAL
+        } else {
+            Self::GAMEPAD_IDLE_INTERVAL
+        };
+        event_loop.set_control_flow(winit::event_loop::ControlFlow::WaitUntil(now + interval));
+    }
+
+    fn handle_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        let steps = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 / 50.0,
+        };
+        self.camera.zoom(Self::ZOOM_STEP.powf(steps));
+        self.update_camera();
+    }
+
+    // sets the target only, advance_animation eases the camera toward it
+    fn zoom_fov(&mut self, factor: f32) {
+        let Projection::Perspective { fov_y } = self.camera.projection else {
+            log::info!("Field of view zoom needs the perspective projection");
+            return;
+        };
+        // nothing refreshed the frame time while idle, do not spend that gap on the first step
+        if self.fov_target.is_none() && !self.turntable && self.track_playback.is_none() {
+            self.last_frame_time = None;
+        }
+        let target = (self.fov_target.unwrap_or(fov_y) * factor).clamp(Self::MIN_FOV_DEGREES.to_radians(), Self::MAX_FOV_DEGREES.to_radians());
+        self.fov_target = Some(target);
+        log::info!("Field of view: {:.1}°", target.to_degrees());
+        self.update_camera();
+    }
+
+    fn ease_fov(&mut self, elapsed: f32) {
+        let Some(target) = self.fov_target else {
+            return;
+        };
+        let Projection::Perspective { fov_y } = &mut self.camera.projection else {
+            self.fov_target = None;
+            return;
+        };
+        *fov_y += (target - *fov_y) * (1.0 - (-elapsed / Self::FOV_SMOOTHING).exp());
+        if (target - *fov_y).abs() < Self::FOV_SNAP {
+            *fov_y = target;
+            self.fov_target = None;
+        }
+        self.update_camera();
+    }
+
+    fn toggle_orbit_activation(&mut self) -> anyhow::Result<()> {
+        self.config.orbit_activation = match self.config.orbit_activation {
+            OrbitActivation::Hold => OrbitActivation::Toggle,
+            OrbitActivation::Toggle => OrbitActivation::Hold,
+        };
+        self.orbiting = false;
+        log::info!("Orbit activation: {:?}", self.config.orbit_activation);
+        self.config.save()
+    }
+
+    fn scale_mouse_sensitivity(&mut self, factor: f32) -> anyhow::Result<()> {
+        self.config.mouse_sensitivity = (self.config.mouse_sensitivity * factor).clamp(Self::MIN_MOUSE_SENSITIVITY, Self::MAX_MOUSE_SENSITIVITY);
+        log::info!("Mouse sensitivity: {:.2}", self.config.mouse_sensitivity);
+        self.config.save()
+    }
+
+    fn toggle_invert_y(&mut self) -> anyhow::Result<()> {
+        self.config.invert_y = !self.config.invert_y;
+        log::info!("Invert Y {}", if self.config.invert_y { "enabled" } else { "disabled" });
+        self.config.save()
+    }
+
+    fn toggle_turntable(&mut self) {
+        self.turntable = !self.turntable;
+        self.last_frame_time = None;
+        log::info!("Turntable {}", if self.turntable { "enabled" } else { "disabled" });
+        self.update_camera();
+    }
+
+    fn toggle_projection(&mut self) {
+        self.fov_target = None;
+        self.camera.toggle_projection();
+        log::info!("Projection: {:?}", self.camera.projection);
+        self.update_camera();
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.step_requested = false;
+        self.last_frame_time = None;
+        log::info!("Animation {} at frame {}", if self.paused { "paused" } else { "resumed" }, self.frame_index);
+        self.update_camera();
+    }
+
+    fn request_step(&mut self) {
+        if !self.paused {
+            return;
+        }
+        self.step_requested = true;
+        if let Some(renderer) = self.renderer.as_ref() {
+            renderer.request_redraw();
+        }
+    }
+
+    fn advance_animation(&mut self) {
+        let now = Instant::now();
+        let elapsed = self.last_frame_time.replace(now).map_or(0.0, |last| (now - last).as_secs_f32());
+        if 0.0 < elapsed {
+            self.frame_interval += (elapsed - self.frame_interval) * 0.1;
+        }
+        let ticks = if self.paused {
+            u32::from(std::mem::take(&mut self.step_requested))
+        } else {
+            self.tick_accumulator = (self.tick_accumulator + elapsed).min(Self::MAX_CATCH_UP);
+            let ticks = (self.tick_accumulator / Self::TICK).floor();
+            self.tick_accumulator -= ticks * Self::TICK;
+            ticks as u32
+        };
+
+        let animating = self.turntable || self.track_playback.is_some();
+        for _ in 0..ticks {
+            self.tick(Self::TICK);
+        }
+        if self.paused && 0 < ticks {
+            log::info!("Stepped to frame {}", self.frame_index);
+        }
+        // keep redrawing while animating, even on frames that did not reach a full tick
+        if animating && (0 < ticks || !self.paused) {
+            self.update_camera();
+        }
+        self.ease_fov(elapsed);
+        self.record_pose();
+    }
+
+    fn toggle_recording(&mut self) {
+        let Some(recording) = self.track_recording.take() else {
+            let path = self.record_path();
+            if !self.overwrite_record && path.exists() {
+                log::error!("Camera track {} already exists, pass --overwrite-track to replace it", path.display());
+                return;
+            }
+            log::info!("Recording camera track to {}", path.display());
+            self.track_recording = Some(TrackRecording {
+                track: Track {
+                    keyframes: vec![Keyframe::from_camera(0.0, &self.camera)],
+                },
+                start: Instant::now(),
+                path,
+            });
+            self.update_camera();
+            return;
+        };
+        let mut track = recording.track;
+        // the pose at the moment recording stopped, unless a sample was just taken
+        let time = recording.start.elapsed().as_secs_f32();
+        if track.duration() < time {
+            track.keyframes.push(Keyframe::from_camera(time, &self.camera));
+        }
+        match track.save(&recording.path, self.overwrite_record) {
+            Ok(()) => log::info!("Saved camera track {} ({} keyframes, {:.1}s)", recording.path.display(), track.keyframes.len(), track.duration()),
+            Err(e) => log::error!("Failed to save camera track: {:?}", e),
+        }
+    }
+
+    fn record_path(&self) -> PathBuf {
+        self.record_path.clone().unwrap_or_else(|| {
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+            PathBuf::from(format!("track-{}.json", timestamp))
+        })
+    }
+
+    // samples by wall clock so the track replays at the speed it was flown, redraws
+    // are kept going meanwhile or poses held without input would be skipped
+    fn record_pose(&mut self) {
+        let Some(recording) = self.track_recording.as_mut() else {
+            return;
+        };
+        let time = recording.start.elapsed().as_secs_f32();
+        if Self::RECORD_INTERVAL <= time - recording.track.duration() {
+            recording.track.keyframes.push(Keyframe::from_camera(time, &self.camera));
+        }
+        if let Some(renderer) = self.renderer.as_ref() {
+            renderer.request_redraw();
+        }
+    }
+
+    fn tick(&mut self, dt: f32) {
+        self.frame_index += 1;
+        if self.turntable {
+            self.camera.turn(dt);
+        }
+        if let Some(playback) = self.track_playback.as_mut() {
+            playback.time += dt;
+            let duration = playback.track.duration();
+            let finished = duration <= playback.time;
+            if finished && playback.looping && 0.0 < duration {
+                playback.time %= duration;
+            }
+            playback.track.apply(playback.time, &mut self.camera);
+            let finished = finished && !playback.looping;
+            self.capture_track_frame();
+            if finished {
+                log::info!("Camera track finished");
+                self.track_playback = None;
+            }
+        }
+    }
+
+    // one image per tick keeps the captured frames evenly spaced in track time
+    fn capture_track_frame(&mut self) {
+        let Some(path) = self.track_playback.as_mut().and_then(|playback| {
+            let path = playback.capture_dir.as_ref()?.join(format!("frame-{:05}.png", playback.captured_frames));
+            playback.captured_frames += 1;
+            Some(path)
+        }) else {
+            return;
+        };
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+        // several ticks can run before the next redraw, the capture needs this one's camera
+        renderer.set_camera(&self.camera);
+        let Some(renderer) = self.renderer.as_ref() else {
+            return;
+        };
+        if let Err(e) = self.save_capture(renderer, &path) {
+            log::error!("Failed to capture track frame {}: {:?}", path.display(), e);
+        }
+    }
+
+    fn reset_camera(&mut self) {
+        self.camera = self.initial_camera;
+        self.orbiting = false;
+        self.fov_target = None;
+        log::info!("Camera reset");
+        self.update_camera();
+    }
+
+    fn reset_clock(&mut self) {
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.reset_clock();
+            log::info!("Shader clock reset");
+            renderer.request_redraw();
+        }
+    }
+
+    fn update_benchmark(&mut self, event_loop: &ActiveEventLoop) {
+        let (Some(benchmark), Some(renderer)) = (self.benchmark.as_mut(), self.renderer.as_ref()) else {
+            return;
+        };
+        let start = *benchmark.start.get_or_insert_with(Instant::now);
+        if renderer.last_frame_stats().presented {
+            benchmark.presented_frames += 1;
+        }
+        let elapsed = start.elapsed();
+        if benchmark.duration <= elapsed {
+            let fps = benchmark.presented_frames as f64 / elapsed.as_secs_f64();
+            println!("Benchmark: {} frames in {:.2}s, {:.1} FPS", benchmark.presented_frames, elapsed.as_secs_f64(), fps);
+            event_loop.exit();
+        } else {
+            renderer.request_redraw();
+        }
+    }
+
+    fn toggle_texture_filter(&mut self) {
+        if let Some(renderer) = self.renderer.as_mut() {
+            let filter = match renderer.texture_filter() {
+                wgpu::FilterMode::Linear => wgpu::FilterMode::Nearest,
+                wgpu::FilterMode::Nearest => wgpu::FilterMode::Linear,
+            };
+            log::info!("Texture filter: {:?}", filter);
+            renderer.set_texture_filter(filter);
+        }
+    }
+
+    // doubles the level up to the maximum, then starts over without anisotropic filtering
+    fn cycle_anisotropy(&mut self) -> anyhow::Result<()> {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return Ok(());
+        };
+        let current = renderer.anisotropy();
+        renderer.set_anisotropy(if current < Self::MAX_ANISOTROPY { current * 2 } else { 1 });
+        log::info!("Anisotropy: {}x", renderer.anisotropy());
+        self.config.anisotropy = renderer.anisotropy();
+        self.config.save()
+    }
+
+    fn cycle_tone_mapper(&mut self) -> anyhow::Result<()> {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return Ok(());
+        };
+        let tone_mapper = renderer.tone_mapper().next();
+        log::info!("Tone mapper: {:?}", tone_mapper);
+        renderer.set_tone_mapper(tone_mapper);
+        self.config.tone_mapper = tone_mapper;
+        self.config.save()
+    }
+
+    fn toggle_fxaa(&mut self) -> anyhow::Result<()> {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return Ok(());
+        };
+        let enabled = !renderer.fxaa_enabled();
+        log::info!("FXAA {}", if enabled { "enabled" } else { "disabled" });
+        renderer.set_fxaa(enabled);
+        self.config.fxaa = enabled;
+        self.config.save()
+    }
+
+    fn cycle_debug_view(&mut self) {
+        if let Some(renderer) = self.renderer.as_mut() {
+            let debug_view = renderer.debug_view().next();
+            log::info!("Debug view: {:?}", debug_view);
+            renderer.set_debug_view(debug_view);
+        }
+        self.update_title();
+    }
+
+    fn toggle_stereo(&mut self) {
+        if let Some(renderer) = self.renderer.as_mut() {
+            let ipd = match renderer.stereo_ipd() {
+                Some(_) => None,
+                None => Some(self.config.stereo_ipd.unwrap_or(Self::DEFAULT_STEREO_IPD)),
+            };
+            match ipd {
+                Some(ipd) => log::info!("Side by side stereo enabled (IPD {:.3})", ipd),
+                None => log::info!("Side by side stereo disabled"),
+            }
+            renderer.set_stereo(ipd);
+        }
+    }
+
+    fn toggle_dither(&mut self) -> anyhow::Result<()> {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return Ok(());
+        };
+        let enabled = !renderer.dither_enabled();
+        log::info!("Dithering {}", if enabled { "enabled" } else { "disabled" });
+        renderer.set_dither(enabled);
+        self.config.dither = Some(enabled);
+        self.config.save()
+    }
+
+    fn toggle_grid(&mut self) {
+        if let Some(renderer) = self.renderer.as_mut() {
+            let enabled = !renderer.grid_enabled();
+            log::info!("Grid {}", if enabled { "enabled" } else { "disabled" });
+            renderer.set_grid(enabled);
+        }
+    }
+
+    fn toggle_always_on_top(&mut self) -> anyhow::Result<()> {
+        let Some(renderer) = self.renderer.as_ref() else {
+            return Ok(());
+        };
+        self.config.always_on_top = !self.config.always_on_top;
+        renderer.window().set_window_level(Self::window_level(self.config.always_on_top));
+        log::info!("Always on top {}", if self.config.always_on_top { "enabled" } else { "disabled" });
+        self.config.save()
+    }
+
+    // the decoration change resizes the client area, the renderer follows through the Resized event
+    fn toggle_decorations(&mut self) -> anyhow::Result<()> {
+        let Some(renderer) = self.renderer.as_ref() else {
+            return Ok(());
+        };
+        self.config.hide_decorations = !self.config.hide_decorations;
+        renderer.window().set_decorations(!self.config.hide_decorations);
+        log::info!("Window decorations {}", if self.config.hide_decorations { "hidden" } else { "shown" });
+        self.config.save()
+    }
+
+    // cross section along x through clip plane 0, everything beyond the offset is cut away
+    fn update_section(&mut self, offset: Option<f32>) {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+        self.section_offset = offset;
+        match offset {
+            Some(offset) => log::info!("Section plane at x = {:.2}", offset),
+            None => log::info!("Section plane disabled"),
+        }
+        if let Err(e) = renderer.set_clip_plane(0, offset.map(|offset| (Vec3::X, offset))) {
+            log::warn!("Failed to set section plane: {:?}", e);
+        }
+    }
+
+    fn toggle_section(&mut self) {
+        let offset = match self.section_offset {
+            Some(_) => None,
+            None => Some(self.camera.target.x),
+        };
+        self.update_section(offset);
+    }
+
+    fn move_section(&mut self, delta: f32) {
+        if let Some(offset) = self.section_offset {
+            self.update_section(Some(offset + delta));
+        }
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let Some(renderer) = self.renderer.as_ref() else {
+            return;
+        };
+        let window = renderer.window();
+        let fullscreen = match window.fullscreen() {
+            Some(_) => None,
+            None => Some(self.fullscreen(window.available_monitors(), window.current_monitor())),
+        };
+        if fullscreen.is_none() {
+            log::info!("Leaving fullscreen");
+        }
+        window.set_fullscreen(fullscreen);
+    }
+
+    fn log_memory_report(&self) {
+        let Some(renderer) = self.renderer.as_ref() else {
+            return;
+        };
+        const MIB: f64 = 1024.0 * 1024.0;
+        match renderer.memory_report() {
+            Some(report) => log::info!(
+                "GPU memory: {:.2} MiB allocated in {} allocations, {:.2} MiB reserved in {} blocks",
+                report.allocated_bytes as f64 / MIB,
+                report.allocations,
+                report.reserved_bytes as f64 / MIB,
+                report.blocks,
+            ),
+            None => log::info!("GPU memory report is not supported by the {:?} backend", renderer.backend()),
+        }
+    }
+
+    fn toggle_hud(&mut self) {
+        self.hud = !self.hud;
+        log::info!("HUD {}", if self.hud { "enabled" } else { "disabled" });
+        if let Some(renderer) = self.renderer.as_ref() {
+            renderer.request_redraw();
+        }
+    }
+
+    fn draw_hud(&mut self) {
+        let Some(renderer) = self.renderer.as_mut().filter(|_| self.hud) else {
+            return;
+        };
+        let fps = if 0.0 < self.frame_interval { 1.0 / self.frame_interval } else { 0.0 };
+        let position = self.camera.position();
+        let mut text = format!("FPS {:.1}\nPOS {:.2} {:.2} {:.2}\nFRAME {}", fps, position.x, position.y, position.z, self.frame_index);
+        // from the previous frame, this one is not rendered yet
+        let stats = renderer.last_frame_stats();
+        text += &format!("\nCPU {:.2} MS\nDRAWS {}", stats.cpu_time.as_secs_f64() * 1000.0, stats.draw_calls);
+        if let Some(gpu_time) = stats.gpu_time {
+            text += &format!("\nGPU {:.2} MS", gpu_time.as_secs_f64() * 1000.0);
+        }
+        renderer.draw_text(&text, 8.0, 8.0, Self::HUD_SCALE, [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    fn toggle_profiler(&mut self) {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+        let enabled = !renderer.profiling();
+        match renderer.set_profiling(enabled) {
+            Ok(()) => log::info!("GPU profiler {}", if enabled { "enabled" } else { "disabled" }),
+            Err(e) => log::warn!("Failed to toggle the GPU profiler: {:?}", e),
+        }
+        renderer.request_redraw();
+    }
+
+    // bottom left, out of the way of the HUD
+    fn draw_profiler(&mut self) {
+        let Some(renderer) = self.renderer.as_mut().filter(|renderer| renderer.profiling()) else {
+            return;
+        };
+        let timings = renderer.pass_timings();
+        let total: Duration = timings.iter().map(|(_, time)| *time).sum();
+        let mut text = timings.iter().map(|(name, time)| format!("{} {:.3} MS\n", name, time.as_secs_f64() * 1000.0)).collect::<String>();
+        text += &format!("SUM {:.3} MS\n", total.as_secs_f64() * 1000.0);
+        text += &format!("GPU {:.3} MS", renderer.gpu_frame_time().unwrap_or_default().as_secs_f64() * 1000.0);
+        let lines = timings.len() as f32 + 2.0;
+        let y = renderer.window().inner_size().height as f32 - 8.0 - lines * TextRenderer::LINE_HEIGHT as f32 * Self::HUD_SCALE;
+        renderer.draw_text(&text, 8.0, y, Self::HUD_SCALE, [1.0, 1.0, 0.4, 1.0]);
+    }
+
+    // shift saves the linear HDR radiance as OpenEXR instead of the displayed PNG
+    fn capture_screenshot(&self) {
+        let Some(renderer) = self.renderer.as_ref() else {
+            return;
+        };
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let linear = self.modifiers.shift_key();
+        let path = PathBuf::from(format!("screenshot-{}.{}", timestamp, if linear { "exr" } else { "png" }));
+        match self.save_capture(renderer, &path) {
+            Ok(()) => log::info!("Saved screenshot {}", path.display()),
+            Err(e) => log::error!("Failed to capture screenshot: {:?}", e),
+        }
+    }
+
+    fn capture_depth_screenshot(&self) {
+        let Some(renderer) = self.renderer.as_ref() else {
+            return;
+        };
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let path = PathBuf::from(format!("depth-{}.png", timestamp));
+        match renderer.capture_depth(&path) {
+            Ok(()) => log::info!("Saved depth heatmap {}", path.display()),
+            Err(e) => log::error!("Failed to capture depth heatmap: {:?}", e),
+        }
+    }
+
+    fn capture_exit_screenshot(&mut self) {
+        let Some(path) = self.exit_screenshot.take() else {
+            return;
+        };
+        let Some(renderer) = self.renderer.as_ref().filter(|_| self.frame_rendered) else {
+            log::warn!("No frame was rendered, skipping exit screenshot {}", path.display());
+            return;
+        };
+        match self.save_capture(renderer, &path) {
+            Ok(()) => log::info!("Saved exit screenshot {}", path.display()),
+            Err(e) => log::error!("Failed to capture exit screenshot: {:?}", e),
+        }
+    }
+
+    fn write_ppm(&self) -> anyhow::Result<()> {
+        let renderer = self.renderer.as_ref().context("Renderer is not initialized")?;
+        let image = renderer.capture_image()?;
+        let mut stdout = std::io::stdout().lock();
+        write!(stdout, "P6\n{} {}\n255\n", image.width(), image.height()).context("Failed to write PPM header")?;
+        for pixel in image.pixels() {
+            stdout.write_all(&pixel.0[..3]).context("Failed to write PPM pixels")?;
+        }
+        stdout.flush().context("Failed to flush PPM output")
+    }
+
+    fn save_capture(&self, renderer: &Renderer, path: &Path) -> anyhow::Result<()> {
+        let linear = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("exr"));
+        if linear {
+            return renderer.capture_frame_exr(path);
+        }
+        match &self.tiled_capture {
+            Some(tiled) => {
+                let image = renderer.capture_tiled(tiled.width, tiled.height, tiled.tile_size)?;
+                image.save(path).with_context(|| format!("Failed to save frame capture {}", path.display()))
+            }
+            None => renderer.capture_frame(path),
+        }
+    }
+
+    fn handle_key(&mut self, event_loop: &ActiveEventLoop, event: KeyEvent) {
+        if !event.state.is_pressed() || event.repeat {
+            return;
+        }
+        let PhysicalKey::Code(key) = event.physical_key else {
+            return;
+        };
+        let Some(action) = self.config.key_bindings.action(key) else {
+            return;
+        };
+        match action {
+            Action::Quit => event_loop.exit(),
+            Action::ToggleOrbitActivation => {
+                if let Err(e) = self.toggle_orbit_activation() {
+                    log::warn!("Failed to persist orbit activation: {:?}", e);
+                }
+            }
+            Action::ToggleTurntable => self.toggle_turntable(),
+            Action::ResetCamera => self.reset_camera(),
+            Action::ToggleProjection => self.toggle_projection(),
+            Action::ZoomOut => self.zoom_fov(1.0 / Self::FOV_STEP),
+            Action::ZoomIn => self.zoom_fov(Self::FOV_STEP),
+            Action::IncreaseMouseSensitivity | Action::DecreaseMouseSensitivity => {
+                let factor = if action == Action::IncreaseMouseSensitivity { Self::MOUSE_SENSITIVITY_STEP } else { 1.0 / Self::MOUSE_SENSITIVITY_STEP };
+                if let Err(e) = self.scale_mouse_sensitivity(factor) {
+                    log::warn!("Failed to persist mouse sensitivity: {:?}", e);
+                }
+            }
+            Action::ToggleInvertY => {
+                if let Err(e) = self.toggle_invert_y() {
+                    log::warn!("Failed to persist invert Y: {:?}", e);
+                }
+            }
+            Action::ResetClock => self.reset_clock(),
+            Action::ToggleTextureFilter => self.toggle_texture_filter(),
+            Action::CycleAnisotropy => {
+                if let Err(e) = self.cycle_anisotropy() {
+                    log::warn!("Failed to persist anisotropy: {:?}", e);
+                }
+            }
+            Action::ToggleSection => self.toggle_section(),
+            Action::SectionBackward => self.move_section(-Self::SECTION_STEP),
+            Action::SectionForward => self.move_section(Self::SECTION_STEP),
+            Action::ToggleGrid => self.toggle_grid(),
+            Action::ToggleHud => self.toggle_hud(),
+            Action::CycleToneMapper => {
+                if let Err(e) = self.cycle_tone_mapper() {
+                    log::warn!("Failed to persist tone mapper: {:?}", e);
+                }
+            }
+            Action::ToggleFxaa => {
+                if let Err(e) = self.toggle_fxaa() {
+                    log::warn!("Failed to persist FXAA: {:?}", e);
+                }
+            }
+            Action::NextScene => self.switch_scene(true),
+            Action::PreviousScene => self.switch_scene(false),
+            Action::NextCamera => self.cycle_scene_camera(!self.modifiers.shift_key()),
+            Action::CycleDebugView => self.cycle_debug_view(),
+            Action::ToggleSolo => self.toggle_solo(self.modifiers.shift_key()),
+            Action::ToggleDither => {
+                if let Err(e) = self.toggle_dither() {
+                    log::warn!("Failed to persist dithering: {:?}", e);
+                }
+            }
+            Action::TogglePause => self.toggle_pause(),
+            Action::Step => self.request_step(),
+            Action::ToggleRecording => self.toggle_recording(),
+            Action::ToggleProfiler => self.toggle_profiler(),
+            Action::ToggleStereo => self.toggle_stereo(),
+            Action::MemoryReport => self.log_memory_report(),
+            Action::ToggleAlwaysOnTop => {
+                if let Err(e) = self.toggle_always_on_top() {
+                    log::warn!("Failed to persist always on top: {:?}", e);
+                }
+            }
+            Action::ToggleDecorations => {
+                if let Err(e) = self.toggle_decorations() {
+                    log::warn!("Failed to persist window decorations: {:?}", e);
+                }
+            }
+            Action::ToggleFullscreen => self.toggle_fullscreen(),
+            Action::Screenshot if self.modifiers.control_key() => self.capture_depth_screenshot(),
+            Action::Screenshot => self.capture_screenshot(),
+        }
+    }
 }
 
-impl ApplicationHandler for App {
+impl ApplicationHandler<AppEvent> for App {
 
     fn resumed(&mut self, _event_loop: &ActiveEventLoop) {}
 
     fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: StartCause) {
-        self.result = match cause {
-            StartCause::Init => self.init_renderer(event_loop),
-            _ => Ok(()),
-        };
-        if self.result.is_err() {
-            event_loop.exit();
+        if let StartCause::Init = cause {
+            self.result = self.init_renderer(event_loop);
+            if self.result.is_err() {
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: AppEvent) {
+        match event {
+            AppEvent::RendererReady(renderer) => {
+                self.result = self.finish_renderer(*renderer);
+                if self.result.is_ok() && self.ppm_output {
+                    self.result = self.write_ppm();
+                    event_loop.exit();
+                }
+                if self.result.is_err() {
+                    event_loop.exit();
+                }
+            }
         }
     }
 
@@ -73,17 +1268,62 @@ impl ApplicationHandler for App {
             WindowEvent::RedrawRequested => {
                 log::info!("Redraw Request");
 
-                let renderer = self.renderer.as_mut().unwrap();
+                // still being created, finish_renderer redraws once it is ready
+                let Some(renderer) = self.renderer.as_ref() else {
+                    return;
+                };
+                // minimized or lost surface, Resized configures it again. Nothing below runs so
+                // no new redraw is requested until then
+                if !renderer.is_ready() {
+                    return;
+                }
 
+                self.advance_animation();
+                self.draw_hud();
+                self.draw_profiler();
+
+                let Some(renderer) = self.renderer.as_mut() else {
+                    return;
+                };
                 renderer.render();
+                log::debug!("Frame stats: {:?}", renderer.last_frame_stats());
+                let presented = renderer.last_frame_stats().presented;
+                self.frame_rendered |= presented;
+                // profiler timings only refresh on rendered frames
+                if presented && renderer.profiling() {
+                    renderer.request_redraw();
+                }
+
+                self.update_benchmark(event_loop);
             },
 			WindowEvent::Resized(_) => {
 				log::info!("Window Resized");
 
-				let renderer = self.renderer.as_mut().unwrap();
-
-				renderer.resize();
+				if let Some(renderer) = self.renderer.as_mut() {
+					renderer.resize();
+				}
 			}
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                self.handle_orbit_button(state);
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Right, .. } => {
+                self.pick();
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.handle_cursor_moved(position);
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_position = None;
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.handle_mouse_wheel(delta);
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.handle_key(event_loop, event);
+            }
             WindowEvent::CloseRequested => {
                 log::info!("Close Requested");
                 event_loop.exit();
@@ -91,4 +1331,14 @@ impl ApplicationHandler for App {
             _ => (),
         }
     }
+
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        #[cfg(feature = "gamepad")]
+        self.update_gamepads(_event_loop);
+    }
+
+    // runs for every way out of the event loop, the renderer is still alive here
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.capture_exit_screenshot();
+    }
 }
\ No newline at end of file
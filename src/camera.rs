@@ -0,0 +1,362 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use glam::{Quat, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+use anyhow::{Context, bail};
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::NoUninit)]
+pub struct CameraUniform {
+	position: [f32; 3],
+	// half the view height, at unit distance for perspective and in world units for orthographic
+	view_scale: f32,
+	forward: [f32; 3],
+	orthographic: u32,
+	right: [f32; 3],
+	_padding1: f32,
+	up: [f32; 3],
+	_padding2: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+	Perspective { fov_y: f32 },
+	// height of the view volume in world units, rays start on the camera plane
+	Orthographic { height: f32 },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+	pub target: Vec3,
+	pub distance: f32,
+	pub yaw: f32,
+	pub pitch: f32,
+	pub projection: Projection,
+	pub turntable_speed: f32,
+}
+
+impl Default for Camera {
+	fn default() -> Self {
+		Self {
+			target: Vec3::ZERO,
+			distance: 6.3,
+			yaw: 0.0,
+			pitch: 0.32,
+			projection: Projection::Perspective { fov_y: 50f32.to_radians() },
+			turntable_speed: 0.5,
+		}
+	}
+}
+
+impl Camera {
+	const MIN_DISTANCE: f32 = 0.1;
+	const MIN_ORTHOGRAPHIC_HEIGHT: f32 = 0.01;
+	const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+	//public
+
+	pub fn position(&self) -> Vec3 {
+		let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+		let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+		self.target + self.distance * Vec3::new(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw)
+	}
+
+	pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
+		self.yaw = (self.yaw + delta_yaw).rem_euclid(std::f32::consts::TAU);
+		self.pitch = (self.pitch + delta_pitch).clamp(-Self::MAX_PITCH, Self::MAX_PITCH);
+	}
+
+	// moves the target over the ground plane, x to the right of the view and y along it
+	pub fn pan(&mut self, delta: Vec2) {
+		let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+		let forward = -Vec3::new(sin_yaw, 0.0, cos_yaw);
+		let right = Vec3::new(cos_yaw, 0.0, -sin_yaw);
+		self.target += delta.x * right + delta.y * forward;
+	}
+
+	pub fn turn(&mut self, dt: f32) {
+		self.orbit(self.turntable_speed * dt, 0.0);
+	}
+
+	pub fn look_at(&mut self, position: Vec3, target: Vec3) {
+		let offset = position - target;
+		self.target = target;
+		self.distance = offset.length().max(Self::MIN_DISTANCE);
+		let direction = offset.normalize_or(Vec3::Z);
+		self.yaw = direction.x.atan2(direction.z).rem_euclid(std::f32::consts::TAU);
+		self.pitch = direction.y.asin().clamp(-Self::MAX_PITCH, Self::MAX_PITCH);
+	}
+
+	// keeps the view direction and fits the sphere into the vertical field of view with some margin
+	pub fn frame(&mut self, center: Vec3, radius: f32) {
+		const MARGIN: f32 = 1.2;
+		self.target = center;
+		match &mut self.projection {
+			Projection::Perspective { fov_y } => self.distance = (MARGIN * radius / (*fov_y * 0.5).sin()).max(Self::MIN_DISTANCE),
+			Projection::Orthographic { height } => *height = (2.0 * MARGIN * radius).max(Self::MIN_ORTHOGRAPHIC_HEIGHT),
+		}
+	}
+
+	// moving closer has no visible effect without perspective, orthographic zoom scales the view instead
+	pub fn zoom(&mut self, factor: f32) {
+		match &mut self.projection {
+			Projection::Perspective { .. } => self.distance = (self.distance * factor).max(Self::MIN_DISTANCE),
+			Projection::Orthographic { height } => *height = (*height * factor).max(Self::MIN_ORTHOGRAPHIC_HEIGHT),
+		}
+	}
+
+	// keeps the framing of the plane through the target
+	pub fn toggle_projection(&mut self) {
+		self.projection = match self.projection {
+			Projection::Perspective { fov_y } => Projection::Orthographic { height: 2.0 * self.distance * (fov_y * 0.5).tan() },
+			Projection::Orthographic { height } => Projection::Perspective { fov_y: 2.0 * (0.5 * height / self.distance).atan() },
+		};
+	}
+
+	// scale from NDC to the view plane at unit distance before the field of view is applied
+	pub fn aspect_ratio(window_width: u32, window_height: u32) -> [f32; 2] {
+		if window_width < window_height {
+			[1.0, window_height as f32 / window_width as f32]
+		} else {
+			[window_width as f32 / window_height as f32, 1.0]
+		}
+	}
+
+	// same construction as primary_ray in the fragment shader, pixel is from the top left
+	pub fn primary_ray(&self, pixel: Vec2, window_width: u32, window_height: u32) -> (Vec3, Vec3) {
+		self.eye_ray(pixel, window_width, window_height, 0.0)
+	}
+
+	// primary_ray of the camera shifted like eye_uniform, pixel is relative to the eye viewport
+	pub fn eye_ray(&self, pixel: Vec2, window_width: u32, window_height: u32, eye_offset: f32) -> (Vec3, Vec3) {
+		let (forward, right, up) = self.basis();
+		let position = self.position() + eye_offset * right;
+		let ndc = pixel * 2.0 / Vec2::new(window_width as f32, window_height as f32) - 1.0;
+		let view = ndc * Vec2::from(Self::aspect_ratio(window_width, window_height)) * self.view_scale();
+		let offset = view.x * right - view.y * up;
+		match self.projection {
+			Projection::Perspective { .. } => (position, (forward + offset).normalize()),
+			Projection::Orthographic { .. } => (position + offset, forward),
+		}
+	}
+
+	pub fn uniform(&self) -> CameraUniform {
+		self.eye_uniform(0.0)
+	}
+
+	// shifted sideways along the right axis, both eyes keep the same view direction
+	pub fn eye_uniform(&self, eye_offset: f32) -> CameraUniform {
+		let (forward, right, up) = self.basis();
+		let position = self.position() + eye_offset * right;
+		CameraUniform {
+			position: position.to_array(),
+			view_scale: self.view_scale(),
+			forward: forward.to_array(),
+			orthographic: matches!(self.projection, Projection::Orthographic { .. }).into(),
+			right: right.to_array(),
+			_padding1: 0.0,
+			up: up.to_array(),
+			_padding2: 0.0,
+		}
+	}
+
+	//private
+
+	fn view_scale(&self) -> f32 {
+		match self.projection {
+			Projection::Perspective { fov_y } => (fov_y * 0.5).tan(),
+			Projection::Orthographic { height } => height * 0.5,
+		}
+	}
+
+	fn basis(&self) -> (Vec3, Vec3, Vec3) {
+		let forward = (self.target - self.position()).normalize();
+		let right = forward.cross(Vec3::Y).normalize();
+		let up = right.cross(forward);
+		(forward, right, up)
+	}
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Keyframe {
+	pub time: f32,
+	pub position: [f32; 3],
+	pub target: [f32; 3],
+	pub fov_y_degrees: f32,
+}
+
+impl Keyframe {
+
+	//public
+
+	// tracks are perspective only, an orthographic view is stored as the field of view framing the same target plane
+	pub fn from_camera(time: f32, camera: &Camera) -> Self {
+		let fov_y = match camera.projection {
+			Projection::Perspective { fov_y } => fov_y,
+			Projection::Orthographic { height } => 2.0 * (0.5 * height / camera.distance).atan(),
+		};
+		Self {
+			time,
+			position: camera.position().to_array(),
+			target: camera.target.to_array(),
+			fov_y_degrees: fov_y.to_degrees(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Track {
+	pub keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+
+	//public
+
+	pub fn load(path: &Path) -> anyhow::Result<Self> {
+		let contents = std::fs::read_to_string(path).with_context(|| format!("Failed to read camera track {}", path.display()))?;
+		let track: Self = serde_json::from_str(&contents).with_context(|| format!("Failed to parse camera track {}", path.display()))?;
+		if track.keyframes.is_empty() {
+			bail!("Camera track {} has no keyframes", path.display());
+		}
+		if track.keyframes.windows(2).any(|pair| pair[1].time <= pair[0].time) {
+			bail!("Camera track {} keyframe times must be strictly increasing", path.display());
+		}
+		Ok(track)
+	}
+
+	// an existing file is only replaced with `overwrite`
+	pub fn save(&self, path: &Path, overwrite: bool) -> anyhow::Result<()> {
+		let file = if overwrite { File::create(path) } else { File::create_new(path) };
+		let file = file.with_context(|| format!("Failed to create camera track {}", path.display()))?;
+		serde_json::to_writer_pretty(BufWriter::new(file), self).with_context(|| format!("Failed to write camera track {}", path.display()))
+	}
+
+	pub fn duration(&self) -> f32 {
+		self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+	}
+
+	// positions follow a Catmull-Rom spline, view directions are slerped
+	pub fn apply(&self, time: f32, camera: &mut Camera) {
+		let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+		if next == 0 || next == self.keyframes.len() {
+			let keyframe = &self.keyframes[next.saturating_sub(1)];
+			camera.look_at(Vec3::from(keyframe.position), Vec3::from(keyframe.target));
+			camera.projection = Projection::Perspective { fov_y: keyframe.fov_y_degrees.to_radians() };
+			return;
+		}
+
+		let k1 = &self.keyframes[next - 1];
+		let k2 = &self.keyframes[next];
+		let k0 = &self.keyframes[(next - 1).saturating_sub(1)];
+		let k3 = &self.keyframes[(next + 1).min(self.keyframes.len() - 1)];
+		let u = (time - k1.time) / (k2.time - k1.time);
+
+		let position = Self::catmull_rom(
+			Vec3::from(k0.position),
+			Vec3::from(k1.position),
+			Vec3::from(k2.position),
+			Vec3::from(k3.position),
+			u,
+		);
+
+		let (offset1, offset2) = (Vec3::from(k1.target) - Vec3::from(k1.position), Vec3::from(k2.target) - Vec3::from(k2.position));
+		let rotation1 = Quat::from_rotation_arc(Vec3::NEG_Z, offset1.normalize_or(Vec3::NEG_Z));
+		let rotation2 = Quat::from_rotation_arc(Vec3::NEG_Z, offset2.normalize_or(Vec3::NEG_Z));
+		let forward = rotation1.slerp(rotation2, u) * Vec3::NEG_Z;
+		let target_distance = offset1.length() + (offset2.length() - offset1.length()) * u;
+
+		camera.look_at(position, position + forward * target_distance);
+		camera.projection = Projection::Perspective { fov_y: (k1.fov_y_degrees + (k2.fov_y_degrees - k1.fov_y_degrees) * u).to_radians() };
+	}
+
+	//private
+
+	fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, u: f32) -> Vec3 {
+		let u2 = u * u;
+		let u3 = u2 * u;
+		0.5 * (2.0 * p1
+			+ (p2 - p0) * u
+			+ (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * u2
+			+ (3.0 * p1 - p0 - 3.0 * p2 + p3) * u3)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn keyframe(time: f32, position: [f32; 3]) -> Keyframe {
+		Keyframe {
+			time,
+			position,
+			target: [0.0, 0.0, 0.0],
+			fov_y_degrees: 40.0 + time * 10.0,
+		}
+	}
+
+	fn track() -> Track {
+		Track {
+			keyframes: vec![keyframe(0.0, [0.0, 1.0, 5.0]), keyframe(1.0, [5.0, 2.0, 0.0]), keyframe(2.5, [0.0, 1.0, -5.0]), keyframe(4.0, [-5.0, 3.0, 0.0])],
+		}
+	}
+
+	fn assert_at_keyframe(camera: &Camera, keyframe: &Keyframe) {
+		let position = camera.position();
+		assert!((position - Vec3::from(keyframe.position)).length() < 1e-4, "expected {:?} but got {}", keyframe.position, position);
+		assert!((camera.target - Vec3::from(keyframe.target)).length() < 1e-4, "expected {:?} but got {}", keyframe.target, camera.target);
+		let Projection::Perspective { fov_y } = camera.projection else {
+			panic!("tracks are perspective only");
+		};
+		assert!((fov_y.to_degrees() - keyframe.fov_y_degrees).abs() < 1e-3);
+	}
+
+	fn write_track(name: &str, contents: &str) -> std::path::PathBuf {
+		let path = std::env::temp_dir().join(format!("rt-{}-{}.json", name, std::process::id()));
+		std::fs::write(&path, contents).unwrap();
+		path
+	}
+
+	#[test]
+	fn keyframes_are_reproduced_at_their_times() {
+		let track = track();
+		for keyframe in &track.keyframes {
+			let mut camera = Camera::default();
+			track.apply(keyframe.time, &mut camera);
+			assert_at_keyframe(&camera, keyframe);
+		}
+	}
+
+	#[test]
+	fn times_outside_the_track_are_clamped() {
+		let track = track();
+		let mut camera = Camera::default();
+		track.apply(-1.0, &mut camera);
+		assert_at_keyframe(&camera, &track.keyframes[0]);
+		track.apply(track.duration() + 1.0, &mut camera);
+		assert_at_keyframe(&camera, track.keyframes.last().unwrap());
+	}
+
+	#[test]
+	fn load_rejects_non_monotonic_times() {
+		for (name, times) in [("decreasing", [0.0, 2.0, 1.0]), ("repeated", [0.0, 1.0, 1.0])] {
+			let track = Track {
+				keyframes: times.iter().map(|&time| keyframe(time, [0.0, 1.0, 5.0])).collect(),
+			};
+			let path = write_track(name, &serde_json::to_string(&track).unwrap());
+			let result = Track::load(&path);
+			std::fs::remove_file(&path).unwrap();
+			assert!(result.is_err(), "{} keyframe times were accepted", name);
+		}
+	}
+
+	#[test]
+	fn load_accepts_increasing_times() {
+		let path = write_track("increasing", &serde_json::to_string(&track()).unwrap());
+		let result = Track::load(&path);
+		std::fs::remove_file(&path).unwrap();
+		assert_eq!(result.unwrap().keyframes.len(), 4);
+	}
+}
@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+	#[command(subcommand)]
+	pub command: Option<Command>,
+
+	/// Enable wgpu validation layers and fail on any GPU validation error
+	#[arg(long)]
+	pub validate: bool,
+
+	/// Cubemap background from six face images
+	#[arg(long, num_args = 6, value_names = ["+X", "-X", "+Y", "-Y", "+Z", "-Z"])]
+	pub skybox: Option<Vec<PathBuf>>,
+
+	/// Load the scene from a RON description instead of the built-in demo
+	#[arg(long, value_name = "PATH")]
+	pub scene: Option<PathBuf>,
+
+	/// Browse the RON scenes in a directory, PageDown and PageUp switch between them
+	#[arg(long, value_name = "DIR", conflicts_with = "scene")]
+	pub scene_dir: Option<PathBuf>,
+
+	/// Start in borderless fullscreen on the monitor with this index
+	#[arg(long, value_name = "INDEX")]
+	pub monitor: Option<usize>,
+
+	/// Render uncapped on the window surface for the given duration and print the average FPS
+	#[arg(long, value_name = "SECONDS", num_args = 0..=1, default_missing_value = "5")]
+	pub no_vsync_benchmark: Option<f64>,
+
+	/// Drive the camera from a JSON keyframe track
+	#[arg(long, value_name = "PATH")]
+	pub camera_track: Option<PathBuf>,
+
+	/// Save every tick of the camera track playback as a numbered PNG in this directory
+	#[arg(long, value_name = "DIR", requires = "camera_track")]
+	pub capture_track: Option<PathBuf>,
+
+	/// Restart the camera track when it reaches its end
+	#[arg(long, requires = "camera_track")]
+	pub loop_track: bool,
+
+	/// Save camera tracks recorded with F5 here instead of a new timestamped file
+	#[arg(long, value_name = "PATH")]
+	pub record_track: Option<PathBuf>,
+
+	/// Replace the --record-track file if it already exists
+	#[arg(long, requires = "record_track")]
+	pub overwrite_track: bool,
+
+	/// Render one frame without showing the window and write it to stdout as binary PPM
+	#[arg(long)]
+	pub ppm: bool,
+
+	/// Save the final frame when the app exits, as linear OpenEXR for .exr paths and PNG otherwise
+	#[arg(long, value_name = "PATH")]
+	pub screenshot_on_exit: Option<PathBuf>,
+
+	/// Render PNG screenshots at this resolution instead of the window size, e.g. 7680x4320
+	#[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size)]
+	pub capture_size: Option<(u32, u32)>,
+
+	/// Largest tile rendered at once for --capture-size, in pixels
+	#[arg(long, value_name = "PIXELS", default_value_t = 2048)]
+	pub tile_size: u32,
+
+	/// Poll the GPU device after every frame instead of only while waiting for readbacks
+	#[arg(long)]
+	pub poll_every_frame: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+	/// Write the WGSL compiled from the WESL sources to a directory, without touching the GPU
+	DumpShaders {
+		out_dir: PathBuf,
+	},
+}
+
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+	let parse = |dimension: &str| dimension.parse::<u32>().ok().filter(|&dimension| 0 < dimension);
+	match value.split_once('x') {
+		Some((width, height)) => parse(width).zip(parse(height)).ok_or_else(|| format!("invalid size \"{}\"", value)),
+		None => Err(format!("expected WIDTHxHEIGHT, got \"{}\"", value)),
+	}
+}
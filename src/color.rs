@@ -0,0 +1,90 @@
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use anyhow::{Context, bail};
+
+// linear RGB, read either as an sRGB hex string ("#29A09A") or as a linear [r, g, b] array
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ColorInput", into = "[f32; 3]")]
+pub struct Color(pub Vec3);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorInput {
+	Hex(String),
+	Linear([f32; 3]),
+}
+
+impl Color {
+
+	//public
+
+	pub fn from_hex(hex: &str) -> anyhow::Result<Self> {
+		let digits = hex.strip_prefix('#').unwrap_or(hex);
+		// from_str_radix alone would also take a sign, as in "+F"
+		if digits.len() != 6 || !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+			bail!("Invalid color \"{}\", expected #RRGGBB", hex);
+		}
+		let mut channels = [0.0; 3];
+		for (channel, pair) in channels.iter_mut().zip(digits.as_bytes().chunks_exact(2)) {
+			let pair = std::str::from_utf8(pair)?;
+			let value = u8::from_str_radix(pair, 16).with_context(|| format!("Invalid color \"{}\", expected #RRGGBB", hex))?;
+			*channel = Self::srgb_to_linear(value as f32 / 255.0);
+		}
+		Ok(Self(Vec3::from_array(channels)))
+	}
+
+	//private
+
+	// IEC 61966-2-1 transfer function, #808080 becomes about 0.216
+	fn srgb_to_linear(value: f32) -> f32 {
+		if value <= 0.04045 {
+			value / 12.92
+		} else {
+			((value + 0.055) / 1.055).powf(2.4)
+		}
+	}
+}
+
+impl TryFrom<ColorInput> for Color {
+	type Error = anyhow::Error;
+
+	fn try_from(input: ColorInput) -> anyhow::Result<Self> {
+		match input {
+			ColorInput::Hex(hex) => Self::from_hex(&hex),
+			ColorInput::Linear(rgb) => Ok(Self(Vec3::from_array(rgb))),
+		}
+	}
+}
+
+impl From<Color> for [f32; 3] {
+	fn from(color: Color) -> Self {
+		color.0.to_array()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn mid_gray_is_linearized() {
+		for hex in ["#808080", "808080"] {
+			let color = Color::from_hex(hex).unwrap();
+			assert!((color.0 - Vec3::splat(0.2158)).abs().max_element() < 1e-4, "{} gave {:?}", hex, color);
+		}
+	}
+
+	#[test]
+	fn extremes_are_exact() {
+		assert_eq!(Color::from_hex("#000000").unwrap().0, Vec3::ZERO);
+		assert_eq!(Color::from_hex("#FFffFF").unwrap().0, Vec3::ONE);
+	}
+
+	#[test]
+	fn malformed_input_is_rejected() {
+		for hex in ["", "#", "#80808", "#8080800", "80808", "8080800", "#80808G", "80808G", "#+F+F+F", "##80808", "#80 80 80"] {
+			assert!(Color::from_hex(hex).is_err(), "{:?} was accepted", hex);
+		}
+	}
+}
@@ -0,0 +1,109 @@
+use crate::app::OrbitActivation;
+use crate::color::Color;
+use crate::input::KeyBindings;
+use crate::renderer::ToneMapper;
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use anyhow::Context;
+
+// background gradient shown without an environment map
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SkyColors {
+	pub top: Color,
+	pub bottom: Color,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GridStyle {
+	pub spacing: f32,
+	pub color: Color,
+}
+
+// exclusive fullscreen resolution, the closest mode the monitor offers is used
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct VideoModeRequest {
+	pub width: u32,
+	pub height: u32,
+	#[serde(default)]
+	pub refresh_rate_hz: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+	pub orbit_activation: OrbitActivation,
+	// scales the orbit speed of mouse drags and of the gamepad look stick
+	pub mouse_sensitivity: f32,
+	// dragging up, or pushing the look stick up, tilts the view up instead of moving the camera up
+	pub invert_y: bool,
+	pub sky: Option<SkyColors>,
+	pub grid: Option<GridStyle>,
+	pub tone_mapper: ToneMapper,
+	pub fxaa: bool,
+	// None dithers 8 bit surfaces only, see RendererConfig::dither
+	pub dither: Option<bool>,
+	// scRGB output on surfaces that offer it, see RendererConfig::hdr
+	pub hdr: bool,
+	// frames the CPU may queue ahead of the GPU, see RendererConfig::max_frame_latency
+	pub max_frame_latency: u32,
+	// anisotropic filtering level of material textures, 1 disables it
+	pub anisotropy: u16,
+	pub always_on_top: bool,
+	pub hide_decorations: bool,
+	// interpupillary distance for the side by side stereo preview, in world units
+	pub stereo_ipd: Option<f32>,
+	// index into the available monitors used when going fullscreen
+	pub monitor: Option<usize>,
+	// exclusive instead of borderless fullscreen when set
+	pub video_mode: Option<VideoModeRequest>,
+	pub key_bindings: KeyBindings,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			orbit_activation: OrbitActivation::default(),
+			mouse_sensitivity: 1.0,
+			invert_y: false,
+			sky: None,
+			grid: None,
+			tone_mapper: ToneMapper::default(),
+			fxaa: false,
+			dither: None,
+			hdr: false,
+			max_frame_latency: 2,
+			anisotropy: 16,
+			always_on_top: false,
+			hide_decorations: false,
+			stereo_ipd: None,
+			monitor: None,
+			video_mode: None,
+			key_bindings: KeyBindings::default(),
+		}
+	}
+}
+
+impl Config {
+	pub const PATH: &str = "rt.ron";
+
+	//public
+
+	pub fn load() -> anyhow::Result<Self> {
+		let path = Path::new(Self::PATH);
+		if !path.exists() {
+			log::info!("No config file found at {}, using defaults", path.display());
+			return Ok(Self::default());
+		}
+		let contents = std::fs::read_to_string(path).with_context(|| format!("Failed to read config file {}", path.display()))?;
+		let config: Self = ron::from_str(&contents).with_context(|| format!("Failed to parse config file {}", path.display()))?;
+		config.key_bindings.validate().with_context(|| format!("Invalid key bindings in config file {}", path.display()))?;
+		Ok(config)
+	}
+
+	pub fn save(&self) -> anyhow::Result<()> {
+		let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).context("Failed to serialize config")?;
+		std::fs::write(Self::PATH, contents).with_context(|| format!("Failed to write config file {}", Self::PATH))
+	}
+}
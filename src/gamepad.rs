@@ -0,0 +1,71 @@
+use gilrs::{Axis, Button, EventType, Gilrs};
+use glam::Vec2;
+
+use anyhow::anyhow;
+
+// sticks of all connected gamepads are summed, so any of them can drive the camera
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GamepadState {
+	pub move_stick: Vec2,
+	pub look_stick: Vec2,
+	// from 0.25 with the left trigger held to 4 with the right one
+	pub speed: f32,
+}
+
+impl GamepadState {
+
+	//public
+
+	pub fn is_active(&self) -> bool {
+		self.move_stick != Vec2::ZERO || self.look_stick != Vec2::ZERO
+	}
+}
+
+pub struct Gamepads {
+	gilrs: Gilrs,
+}
+
+impl Gamepads {
+	const DEAD_ZONE: f32 = 0.15;
+
+	//public
+
+	pub fn new() -> anyhow::Result<Self> {
+		let gilrs = Gilrs::new().map_err(|e| anyhow!("Failed to initialize gamepad input: {}", e))?;
+		for (_, gamepad) in gilrs.gamepads() {
+			log::info!("Gamepad found: {}", gamepad.name());
+		}
+		Ok(Self { gilrs })
+	}
+
+	// drains pending events, only hot plugging needs them since the state is read directly
+	pub fn poll(&mut self) -> GamepadState {
+		while let Some(event) = self.gilrs.next_event() {
+			match event.event {
+				EventType::Connected => log::info!("Gamepad connected: {}", self.gilrs.gamepad(event.id).name()),
+				EventType::Disconnected => log::info!("Gamepad disconnected: {}", self.gilrs.gamepad(event.id).name()),
+				_ => (),
+			}
+		}
+
+		let mut state = GamepadState::default();
+		let (mut faster, mut slower) = (0.0f32, 0.0f32);
+		for (_, gamepad) in self.gilrs.gamepads() {
+			state.move_stick += Self::stick(gamepad.value(Axis::LeftStickX), gamepad.value(Axis::LeftStickY));
+			state.look_stick += Self::stick(gamepad.value(Axis::RightStickX), gamepad.value(Axis::RightStickY));
+			let trigger = |button| gamepad.button_data(button).map_or(0.0, |data| data.value());
+			faster = faster.max(trigger(Button::RightTrigger2));
+			slower = slower.max(trigger(Button::LeftTrigger2));
+		}
+		state.speed = 1.0 + 3.0 * faster - 0.75 * slower;
+		state
+	}
+
+	//private
+
+	// radial so diagonals are not cut off, values are up and right positive
+	fn stick(x: f32, y: f32) -> Vec2 {
+		let value = Vec2::new(x, y);
+		if value.length() < Self::DEAD_ZONE { Vec2::ZERO } else { value }
+	}
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use winit::keyboard::KeyCode;
+
+use anyhow::bail;
+
+// everything the keyboard can trigger, modifiers on the bound key pick variants of some
+// actions: Shift reverses NextCamera, Shift also frames the camera on the ToggleSolo object,
+// Shift saves Screenshot as EXR and Ctrl as a depth heatmap
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+	Quit,
+	ToggleOrbitActivation,
+	ToggleTurntable,
+	ResetCamera,
+	ToggleProjection,
+	ZoomOut,
+	ZoomIn,
+	IncreaseMouseSensitivity,
+	DecreaseMouseSensitivity,
+	ToggleInvertY,
+	ResetClock,
+	ToggleTextureFilter,
+	CycleAnisotropy,
+	ToggleSection,
+	SectionBackward,
+	SectionForward,
+	ToggleGrid,
+	ToggleHud,
+	CycleToneMapper,
+	ToggleFxaa,
+	NextScene,
+	PreviousScene,
+	NextCamera,
+	CycleDebugView,
+	ToggleSolo,
+	ToggleDither,
+	TogglePause,
+	Step,
+	ToggleRecording,
+	ToggleProfiler,
+	ToggleStereo,
+	MemoryReport,
+	ToggleAlwaysOnTop,
+	ToggleDecorations,
+	ToggleFullscreen,
+	Screenshot,
+}
+
+// config entries replace the default key of their action, actions left out keep it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "BTreeMap<Action, KeyCode>", into = "BTreeMap<Action, KeyCode>")]
+pub struct KeyBindings {
+	keys: BTreeMap<Action, KeyCode>,
+}
+
+impl Default for KeyBindings {
+	fn default() -> Self {
+		Self {
+			keys: Self::DEFAULT_KEYS.into_iter().collect(),
+		}
+	}
+}
+
+impl From<BTreeMap<Action, KeyCode>> for KeyBindings {
+	fn from(keys: BTreeMap<Action, KeyCode>) -> Self {
+		let mut bindings = Self::default();
+		bindings.keys.extend(keys);
+		bindings
+	}
+}
+
+impl From<KeyBindings> for BTreeMap<Action, KeyCode> {
+	fn from(bindings: KeyBindings) -> Self {
+		bindings.keys
+	}
+}
+
+impl KeyBindings {
+	// Quit has no default key so nothing closes the window by accident
+	const DEFAULT_KEYS: [(Action, KeyCode); 35] = [
+		(Action::ToggleOrbitActivation, KeyCode::KeyO),
+		(Action::ToggleTurntable, KeyCode::KeyT),
+		(Action::ResetCamera, KeyCode::KeyR),
+		(Action::ToggleProjection, KeyCode::KeyV),
+		(Action::ZoomOut, KeyCode::Minus),
+		(Action::ZoomIn, KeyCode::Equal),
+		(Action::IncreaseMouseSensitivity, KeyCode::KeyL),
+		(Action::DecreaseMouseSensitivity, KeyCode::KeyK),
+		(Action::ToggleInvertY, KeyCode::KeyI),
+		(Action::ResetClock, KeyCode::Home),
+		(Action::ToggleTextureFilter, KeyCode::KeyF),
+		(Action::CycleAnisotropy, KeyCode::KeyN),
+		(Action::ToggleSection, KeyCode::KeyC),
+		(Action::SectionBackward, KeyCode::BracketLeft),
+		(Action::SectionForward, KeyCode::BracketRight),
+		(Action::ToggleGrid, KeyCode::KeyG),
+		(Action::ToggleHud, KeyCode::KeyH),
+		(Action::CycleToneMapper, KeyCode::KeyM),
+		(Action::ToggleFxaa, KeyCode::KeyA),
+		(Action::NextScene, KeyCode::PageDown),
+		(Action::PreviousScene, KeyCode::PageUp),
+		(Action::NextCamera, KeyCode::Tab),
+		(Action::CycleDebugView, KeyCode::KeyB),
+		(Action::ToggleSolo, KeyCode::KeyS),
+		(Action::ToggleDither, KeyCode::KeyD),
+		(Action::TogglePause, KeyCode::KeyP),
+		(Action::Step, KeyCode::Period),
+		(Action::ToggleRecording, KeyCode::F5),
+		(Action::ToggleProfiler, KeyCode::F6),
+		(Action::ToggleStereo, KeyCode::F7),
+		(Action::MemoryReport, KeyCode::F8),
+		(Action::ToggleAlwaysOnTop, KeyCode::F9),
+		(Action::ToggleDecorations, KeyCode::F10),
+		(Action::ToggleFullscreen, KeyCode::F11),
+		(Action::Screenshot, KeyCode::F12),
+	];
+
+	//public
+
+	pub fn validate(&self) -> anyhow::Result<()> {
+		for (index, (action, key)) in self.keys.iter().enumerate() {
+			if let Some((other, _)) = self.keys.iter().skip(index + 1).find(|(_, other_key)| *other_key == key) {
+				bail!("Key {:?} is bound to both {:?} and {:?}", key, action, other);
+			}
+		}
+		Ok(())
+	}
+
+	pub fn action(&self, key: KeyCode) -> Option<Action> {
+		self.keys.iter().find(|(_, bound)| **bound == key).map(|(action, _)| *action)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn defaults_are_valid() {
+		let bindings = KeyBindings::default();
+		bindings.validate().unwrap();
+		assert_eq!(bindings.action(KeyCode::KeyG), Some(Action::ToggleGrid));
+		assert_eq!(bindings.action(KeyCode::Escape), None);
+	}
+
+	#[test]
+	fn partial_map_keeps_other_defaults() {
+		let bindings: KeyBindings = ron::from_str("{ToggleGrid: KeyX, Quit: Escape}").unwrap();
+		bindings.validate().unwrap();
+		assert_eq!(bindings.action(KeyCode::KeyX), Some(Action::ToggleGrid));
+		assert_eq!(bindings.action(KeyCode::Escape), Some(Action::Quit));
+		assert_eq!(bindings.action(KeyCode::KeyG), None);
+		assert_eq!(bindings.action(KeyCode::KeyH), Some(Action::ToggleHud));
+		assert_eq!(bindings.action(KeyCode::F12), Some(Action::Screenshot));
+	}
+
+	#[test]
+	fn two_actions_on_one_key_are_rejected() {
+		let bindings = KeyBindings::from(BTreeMap::from([(Action::ToggleGrid, KeyCode::KeyX), (Action::ToggleHud, KeyCode::KeyX)]));
+		assert!(bindings.validate().is_err());
+	}
+
+	#[test]
+	fn override_taking_a_default_key_is_rejected() {
+		let bindings = KeyBindings::from(BTreeMap::from([(Action::ToggleGrid, KeyCode::KeyH)]));
+		assert!(bindings.validate().is_err());
+	}
+}
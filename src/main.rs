@@ -1,10 +1,83 @@
 mod app;
+mod camera;
+mod cli;
+mod color;
+mod config;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod input;
+mod profiler;
 mod renderer;
+mod scene;
+mod text;
+mod texture;
 
 use app::App;
+use camera::Track;
+use clap::Parser;
+use cli::{Cli, Command};
+use config::Config;
+use renderer::{DevicePolling, Renderer, RendererConfig};
+use scene::SceneManager;
+use std::time::Duration;
+
+use anyhow::Context;
 
 fn main() -> anyhow::Result<()> {
     env_logger::init();
-    let app = App::default();
+    let cli = Cli::parse();
+    if let Some(Command::DumpShaders { out_dir }) = &cli.command {
+        return Renderer::dump_shaders(out_dir);
+    }
+    let config = Config::load()?;
+    let renderer_config = RendererConfig {
+        validate: cli.validate,
+        tone_mapper: config.tone_mapper,
+        fxaa: config.fxaa,
+        dither: config.dither,
+        hdr: config.hdr,
+        max_frame_latency: config.max_frame_latency,
+        anisotropy: config.anisotropy,
+        device_polling: if cli.poll_every_frame { DevicePolling::PerFrame } else { DevicePolling::OnReadback },
+        ..RendererConfig::default()
+    };
+    let mut app = App::new(config, renderer_config);
+    if let Some(faces) = cli.skybox {
+        let faces = faces.try_into().map_err(|_| anyhow::anyhow!("--skybox expects exactly six images"))?;
+        app = app.with_skybox(faces);
+    }
+    if let Some(path) = cli.scene {
+        app = app.with_scene(scene::load_ron(&path)?);
+    }
+    if let Some(dir) = cli.scene_dir {
+        let mut scenes = SceneManager::from_dir(&dir)?;
+        let scene = scenes.next().ok_or_else(|| anyhow::anyhow!("No scene in {} could be loaded", dir.display()))?;
+        app = app.with_scene(scene).with_scene_manager(scenes);
+    }
+    if let Some(index) = cli.monitor {
+        app = app.with_fullscreen_monitor(index);
+    }
+    if let Some(seconds) = cli.no_vsync_benchmark {
+        app = app.with_benchmark(Duration::from_secs_f64(seconds));
+    }
+    if let Some(path) = cli.camera_track {
+        app = app.with_camera_track(Track::load(&path)?, cli.loop_track);
+    }
+    if let Some(dir) = cli.capture_track {
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create track capture directory {}", dir.display()))?;
+        app = app.with_track_capture(dir);
+    }
+    if let Some(path) = cli.record_track {
+        app = app.with_track_recording(path, cli.overwrite_track);
+    }
+    if let Some((width, height)) = cli.capture_size {
+        app = app.with_tiled_capture(width, height, cli.tile_size);
+    }
+    if cli.ppm {
+        app = app.with_ppm_output();
+    }
+    if let Some(path) = cli.screenshot_on_exit {
+        app = app.with_screenshot_on_exit(path);
+    }
     app.run()
 }
\ No newline at end of file
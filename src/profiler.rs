@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+
+// GPU time per render pass from timestamp queries. Only a few frames per second are
+// timed, each one is read back right after its submission, which stalls that frame
+// but keeps the readback as simple as the other debug readbacks
+pub struct GpuProfiler {
+	query_set: wgpu::QuerySet,
+	resolve_buffer: wgpu::Buffer,
+	readback_buffer: wgpu::Buffer,
+	// nanoseconds per timestamp tick
+	timestamp_period: f32,
+	last_sample: Option<Instant>,
+	timings: Vec<(&'static str, Duration)>,
+	// from the start of the first timed pass to the end of the last one
+	frame_time: Option<Duration>,
+}
+
+impl GpuProfiler {
+	pub const MAX_PASSES: u32 = 4;
+	const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+	//public
+
+	// the device needs the TIMESTAMP_QUERY feature
+	pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+		let query_count = 2 * Self::MAX_PASSES;
+		let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+			label: Some("Profiler Query Set"),
+			ty: wgpu::QueryType::Timestamp,
+			count: query_count,
+		});
+		let size = u64::from(query_count) * wgpu::QUERY_SIZE as u64;
+		let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Profiler Resolve Buffer"),
+			size,
+			usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+			mapped_at_creation: false,
+		});
+		let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Profiler Readback Buffer"),
+			size,
+			usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+		Self {
+			query_set,
+			resolve_buffer,
+			readback_buffer,
+			timestamp_period: queue.get_timestamp_period(),
+			last_sample: None,
+			timings: Vec::new(),
+			frame_time: None,
+		}
+	}
+
+	pub fn sample_due(&self) -> bool {
+		self.last_sample.is_none_or(|last| Self::SAMPLE_INTERVAL <= last.elapsed())
+	}
+
+	// slot is the index of the pass among the timed passes of the frame
+	pub fn timestamp_writes(&self, slot: u32) -> wgpu::RenderPassTimestampWrites<'_> {
+		wgpu::RenderPassTimestampWrites {
+			query_set: &self.query_set,
+			beginning_of_pass_write_index: Some(2 * slot),
+			end_of_pass_write_index: Some(2 * slot + 1),
+		}
+	}
+
+	pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder, pass_count: u32) {
+		let query_count = 2 * pass_count;
+		encoder.resolve_query_set(&self.query_set, 0..query_count, &self.resolve_buffer, 0);
+		encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, u64::from(query_count) * wgpu::QUERY_SIZE as u64);
+	}
+
+	// blocks until the frame that resolved `passes` has finished on the GPU
+	pub fn read(&mut self, device: &wgpu::Device, passes: &[&'static str]) -> anyhow::Result<()> {
+		self.last_sample = Some(Instant::now());
+		let slice = self.readback_buffer.slice(..);
+		let (sender, receiver) = std::sync::mpsc::channel();
+		slice.map_async(wgpu::MapMode::Read, move |result| {
+			let _ = sender.send(result);
+		});
+		device.poll(wgpu::PollType::wait_indefinitely()).context("Failed to wait for timestamp readback")?;
+		receiver.recv().context("Timestamp readback callback was dropped")?.context("Failed to map timestamp readback buffer")?;
+
+		let timestamps: Vec<u64> = bytemuck::pod_collect_to_vec(&slice.get_mapped_range());
+		self.readback_buffer.unmap();
+		let timestamps = &timestamps[..2 * passes.len()];
+		self.timings = passes.iter().zip(timestamps.chunks_exact(2)).map(|(name, pair)| (*name, self.ticks_to_duration(pair[0], pair[1]))).collect();
+		self.frame_time = timestamps.first().zip(timestamps.last()).map(|(&start, &end)| self.ticks_to_duration(start, end));
+		if let (Some(total), Some(frame_time)) = (self.total(), self.frame_time) && frame_time.abs_diff(total) > frame_time / 4 {
+			log::debug!("GPU pass times add up to {:?} but the frame took {:?}", total, frame_time);
+		}
+		Ok(())
+	}
+
+	// from the last timed frame
+	pub fn timings(&self) -> &[(&'static str, Duration)] {
+		&self.timings
+	}
+
+	pub fn total(&self) -> Option<Duration> {
+		(!self.timings.is_empty()).then(|| self.timings.iter().map(|(_, time)| *time).sum())
+	}
+
+	// includes the gaps between passes, close to total unless the GPU idles between them
+	pub fn frame_time(&self) -> Option<Duration> {
+		self.frame_time
+	}
+
+	//private
+
+	fn ticks_to_duration(&self, start: u64, end: u64) -> Duration {
+		let nanoseconds = end.saturating_sub(start) as f64 * f64::from(self.timestamp_period);
+		Duration::from_nanos(nanoseconds as u64)
+	}
+}
@@ -1,45 +1,473 @@
+use crate::camera::{Camera, CameraUniform};
+use crate::profiler::GpuProfiler;
+use crate::scene::{Material, Plane, PointLight, Primitive, Scene, Sphere};
+use crate::text::TextRenderer;
+use crate::texture::{self, Texture};
+
+use std::path::Path;
 use std::sync::Arc;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
 use winit::window::Window;
 use wesl::include_wesl;
 
-use anyhow::{Context, anyhow};
+use anyhow::{Context, anyhow, bail};
 
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::NoUninit)]
 struct Immediate {
+	// size of the image the scene is ray cast into
 	window_size: [u32; 2],
+	// added to fragment positions before generating rays, negative to move the image into
+	// a viewport and positive to render one tile of a larger image
+	pixel_offset: [i32; 2],
 	aspect_ratio: [f32; 2],
+	point_light_count: u32,
+	output_mode: u32,
+	has_environment: u32,
+	sphere_count: u32,
+	plane_count: u32,
+	tone_mapper: u32,
+	debug_view: u32,
+	dither: u32,
+	// object id of the only primitive traced, 0 traces the whole scene
+	solo_object: u32,
+	// the shader struct is rounded up to the alignment of its vec2 members
+	_padding: u32,
 }
 
 impl Immediate {
 	pub fn new(window_width: u32, window_height: u32) -> Self {
 		Self {
 			window_size: [window_width, window_height],
-			aspect_ratio: Self::compute_aspect_ratio(window_width, window_height),
+			pixel_offset: [0, 0],
+			aspect_ratio: Camera::aspect_ratio(window_width, window_height),
+			point_light_count: 0,
+			output_mode: SurfaceOutput::Sdr.shader_mode(),
+			has_environment: 0,
+			sphere_count: 0,
+			plane_count: 0,
+			tone_mapper: 0,
+			debug_view: 0,
+			dither: 0,
+			solo_object: 0,
+			_padding: 0,
 		}
 	}
 
 	pub fn update_window_size(&mut self, window_width: u32, window_height: u32) {
 		self.window_size = [window_width, window_height];
-		self.aspect_ratio = Self::compute_aspect_ratio(window_width, window_height);
+		self.aspect_ratio = Camera::aspect_ratio(window_width, window_height);
 	}
 
-	pub fn compute_aspect_ratio(window_width: u32, window_height: u32) -> [f32; 2] {
-		if window_width < window_height {
-			[1.0, window_height as f32 / window_width as f32]
-		} else {
-			[window_width as f32 / window_height as f32, 1.0]
+	// a sub rectangle of the window, used for side by side stereo
+	pub fn update_viewport(&mut self, x: u32, y: u32, width: u32, height: u32) {
+		self.update_window_size(width, height);
+		self.pixel_offset = [-(x as i32), -(y as i32)];
+	}
+
+	// the tile at x, y of an image_width x image_height frame, rendered from the top left of the target
+	pub fn update_tile(&mut self, x: u32, y: u32, image_width: u32, image_height: u32) {
+		self.update_window_size(image_width, image_height);
+		self.pixel_offset = [x as i32, y as i32];
+	}
+
+	pub fn update_scene_counts(&mut self, scene: &Scene) {
+		self.point_light_count = scene.point_lights().len() as u32;
+		self.sphere_count = scene.spheres().len() as u32;
+		self.plane_count = scene.planes().len() as u32;
+	}
+
+	pub fn update_output_mode(&mut self, surface_output: SurfaceOutput) {
+		self.output_mode = surface_output.shader_mode();
+	}
+
+	pub fn update_tone_mapper(&mut self, tone_mapper: ToneMapper) {
+		self.tone_mapper = tone_mapper.shader_mode();
+	}
+
+	pub fn update_debug_view(&mut self, debug_view: DebugView) {
+		self.debug_view = debug_view.shader_mode();
+	}
+
+	pub fn update_dither(&mut self, dither: bool) {
+		self.dither = dither.into();
+	}
+
+	pub fn update_has_environment(&mut self, has_environment: bool) {
+		self.has_environment = has_environment.into();
+	}
+
+	pub fn update_solo_object(&mut self, object_id: Option<u32>) {
+		self.solo_object = object_id.unwrap_or(0);
+	}
+}
+
+// one camera bind group per eye, the shared globals buffer stays bound in both
+struct StereoEyes {
+	// distance between the eyes in world units
+	ipd: f32,
+	left_buffer: wgpu::Buffer,
+	right_buffer: wgpu::Buffer,
+	left_bind_group: wgpu::BindGroup,
+	right_bind_group: wgpu::BindGroup,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::NoUninit)]
+struct SkyGradient {
+	top: [f32; 3],
+	_padding0: f32,
+	bottom: [f32; 3],
+	_padding1: f32,
+}
+
+impl SkyGradient {
+	fn new(top: [f32; 3], bottom: [f32; 3]) -> Self {
+		Self {
+			top,
+			_padding0: 0.0,
+			bottom,
+			_padding1: 0.0,
 		}
 	}
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::NoUninit)]
+struct GridUniform {
+	color: [f32; 3],
+	spacing: f32,
+	// in pixels
+	line_width: f32,
+	fade_distance: f32,
+	enabled: u32,
+	_padding: u32,
+}
+
+impl Default for GridUniform {
+	fn default() -> Self {
+		Self {
+			color: [0.6, 0.6, 0.6],
+			spacing: 1.0,
+			line_width: 1.0,
+			fade_distance: 50.0,
+			enabled: 0,
+			_padding: 0,
+		}
+	}
+}
+
+// per frame values for animated shader effects
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::NoUninit)]
+struct Globals {
+	// seconds since the renderer clock was started or reset
+	time: f32,
+	frame_index: u32,
+	resolution: [f32; 2],
+}
+
+// edge detection and blur span of the FXAA pass, the defaults follow the FXAA 3.11 console preset
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::NoUninit)]
+struct FxaaImmediate {
+	edge_threshold: f32,
+	edge_threshold_min: f32,
+	span_max: f32,
+	reduce_mul: f32,
+}
+
+impl Default for FxaaImmediate {
+	fn default() -> Self {
+		Self {
+			edge_threshold: 0.125,
+			edge_threshold_min: 0.0312,
+			span_max: 8.0,
+			reduce_mul: 0.125,
+		}
+	}
+}
+
+// intermediate color target the scene is ray cast into before the FXAA pass
+struct FxaaTarget {
+	view: wgpu::TextureView,
+	bind_group: wgpu::BindGroup,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurfaceOutput {
+	Sdr,
+	// scRGB: linear Rec.709 primaries where 1.0 maps to 80 nits
+	HdrLinear,
+}
+
+impl SurfaceOutput {
+	fn shader_mode(self) -> u32 {
+		match self {
+			SurfaceOutput::Sdr => 0,
+			SurfaceOutput::HdrLinear => 1,
+		}
+	}
+}
+
+// when wgpu gets to run map_async and submitted work callbacks. A dedicated thread blocking in
+// poll would fire them as soon as the GPU finishes, but it spins whenever the queue is empty
+// and has to be joined before the device goes away, so polling stays on the render thread.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DevicePolling {
+	// readbacks wait for their own submission, callbacks of other work fire during the next readback
+	#[default]
+	OnReadback,
+	// additionally polls without blocking after every presented frame
+	PerFrame,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ToneMapper {
+	// linear passthrough, only the surface transfer function is applied
+	#[default]
+	None,
+	Reinhard,
+	Aces,
+	AgX,
+}
+
+impl ToneMapper {
+
+	//public
+
+	pub fn next(self) -> Self {
+		match self {
+			ToneMapper::None => ToneMapper::Reinhard,
+			ToneMapper::Reinhard => ToneMapper::Aces,
+			ToneMapper::Aces => ToneMapper::AgX,
+			ToneMapper::AgX => ToneMapper::None,
+		}
+	}
+
+	//private
+
+	fn shader_mode(self) -> u32 {
+		match self {
+			ToneMapper::None => 0,
+			ToneMapper::Reinhard => 1,
+			ToneMapper::Aces => 2,
+			ToneMapper::AgX => 3,
+		}
+	}
+}
+
+// raw surface attributes shown instead of the lit image, the grid and tone mapping are skipped
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DebugView {
+	#[default]
+	Shaded,
+	Normals,
+	// brighter is closer, misses are black
+	Depth,
+	Uvs,
+	Albedo,
+	// viridis from yellow up close to dark purple far away, misses are black
+	DepthHeatmap,
+}
+
+impl DebugView {
+
+	//public
+
+	pub fn next(self) -> Self {
+		match self {
+			DebugView::Shaded => DebugView::Normals,
+			DebugView::Normals => DebugView::Depth,
+			DebugView::Depth => DebugView::Uvs,
+			DebugView::Uvs => DebugView::Albedo,
+			DebugView::Albedo => DebugView::DepthHeatmap,
+			DebugView::DepthHeatmap => DebugView::Shaded,
+		}
+	}
+
+	//private
+
+	fn shader_mode(self) -> u32 {
+		match self {
+			DebugView::Shaded => 0,
+			DebugView::Normals => 1,
+			DebugView::Depth => 2,
+			DebugView::Uvs => 3,
+			DebugView::Albedo => 4,
+			DebugView::DepthHeatmap => 5,
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct RendererConfig {
+	pub backends: wgpu::Backends,
+	// HDR surfaces are mainly exposed by DX12 on Windows with HDR enabled in the
+	// display settings, other backends usually fall back to SDR. Only 16 bit float
+	// surfaces are taken as HDR (scRGB): wgpu does not expose the surface color space,
+	// so a 10 bit surface is not known to be PQ encoded and is left alone.
+	pub hdr: bool,
+	// enables backend validation layers and turns every validation error raised
+	// while creating GPU resources into a fatal error
+	pub validate: bool,
+	// number of frames the CPU may queue ahead of the GPU: 1 gives the lowest
+	// input latency but can starve the GPU, higher values smooth out frame pacing
+	// at the cost of latency
+	pub max_frame_latency: u32,
+	pub anisotropy: u16,
+	// applies to magnification, minification and mip selection alike
+	pub texture_filter: wgpu::FilterMode,
+	pub tone_mapper: ToneMapper,
+	// post-process anti-aliasing, costs an extra fullscreen pass and one color target
+	pub fxaa: bool,
+	// breaks up banding on SDR surfaces, None dithers 8 bit surfaces only. HDR outputs are never dithered
+	pub dither: Option<bool>,
+	// modes the surface does not support fall back to AutoNoVsync
+	pub present_mode: wgpu::PresentMode,
+	pub device_polling: DevicePolling,
+}
+
+impl Default for RendererConfig {
+	fn default() -> Self {
+		Self {
+			backends: wgpu::Backends::from_env().unwrap_or(wgpu::Backends::all()),
+			hdr: false,
+			validate: false,
+			max_frame_latency: 2,
+			anisotropy: 16,
+			texture_filter: wgpu::FilterMode::Linear,
+			tone_mapper: ToneMapper::None,
+			fxaa: false,
+			dither: None,
+			present_mode: wgpu::PresentMode::AutoVsync,
+			device_polling: DevicePolling::OnReadback,
+		}
+	}
+}
+
+impl RendererConfig {
+	const BACKEND_PREFERENCE_ORDER: [wgpu::Backends; 5] = [
+		wgpu::Backends::VULKAN,
+		wgpu::Backends::METAL,
+		wgpu::Backends::DX12,
+		wgpu::Backends::GL,
+		wgpu::Backends::BROWSER_WEBGPU,
+	];
+
+	//public
+
+	pub fn enabled_backends(&self) -> impl Iterator<Item = wgpu::Backends> + '_ {
+		Self::BACKEND_PREFERENCE_ORDER.into_iter().filter(|backend| self.backends.contains(*backend))
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameStats {
+	pub cpu_time: Duration,
+	// total of the last profiled frame, None while the profiler is off
+	pub gpu_time: Option<Duration>,
+	pub draw_calls: u32,
+	pub presented: bool,
+}
+
+// snapshot of the backend allocator, block sizes include memory reserved for future allocations
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryReport {
+	pub allocated_bytes: u64,
+	pub reserved_bytes: u64,
+	pub allocations: usize,
+	pub blocks: usize,
+}
+
+struct SceneBuffers {
+	point_lights: wgpu::Buffer,
+	materials: wgpu::Buffer,
+	spheres: wgpu::Buffer,
+	planes: wgpu::Buffer,
+}
+
+impl SceneBuffers {
+
+	//public
+
+	pub fn new(device: &wgpu::Device, scene: &Scene) -> Self {
+		Self {
+			point_lights: Self::create_storage_buffer(device, "Point Light Buffer", scene.point_lights(), scene.max_point_lights()),
+			materials: Self::create_storage_buffer(device, "Material Buffer", scene.materials(), 0),
+			spheres: Self::create_storage_buffer(device, "Sphere Buffer", scene.spheres(), 0),
+			planes: Self::create_storage_buffer(device, "Plane Buffer", scene.planes(), 0),
+		}
+	}
+
+	//private
+
+	// a zero sized storage binding is invalid, keep room for at least one element
+	fn storage_buffer_size<T>(len: usize, capacity: usize) -> wgpu::BufferAddress {
+		(capacity.max(len).max(1) * size_of::<T>()) as wgpu::BufferAddress
+	}
+
+	fn create_storage_buffer<T: bytemuck::NoUninit>(device: &wgpu::Device, label: &str, data: &[T], capacity: usize) -> wgpu::Buffer {
+		let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some(label),
+			size: Self::storage_buffer_size::<T>(data.len(), capacity),
+			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+			mapped_at_creation: true,
+		});
+		let bytes: &[u8] = bytemuck::cast_slice(data);
+		buffer.slice(..).get_mapped_range_mut()[..bytes.len()].copy_from_slice(bytes);
+		buffer.unmap();
+		buffer
+	}
+}
+
 pub struct Renderer {
 	render_pipeline: wgpu::RenderPipeline,
+	render_pipeline_layout: wgpu::PipelineLayout,
+	scene_bind_group_layout: wgpu::BindGroupLayout,
+	scene_bind_group: wgpu::BindGroup,
+	scene_buffers: SceneBuffers,
+	material_texture: Texture,
+	material_sampler: wgpu::Sampler,
+	camera_buffer: wgpu::Buffer,
+	globals_buffer: wgpu::Buffer,
+	camera_bind_group_layout: wgpu::BindGroupLayout,
+	camera_bind_group: wgpu::BindGroup,
+	camera: Camera,
+	stereo: Option<StereoEyes>,
+	clock_start: Instant,
+	frame_index: u32,
+	environment_bind_group_layout: wgpu::BindGroupLayout,
+	environment_sampler: wgpu::Sampler,
+	environment_bind_group: wgpu::BindGroup,
+	sky_gradient_buffer: wgpu::Buffer,
+	grid: GridUniform,
+	grid_buffer: wgpu::Buffer,
+	clip_planes: [[f32; 4]; Renderer::MAX_CLIP_PLANES],
+	clip_plane_buffer: wgpu::Buffer,
+	overlay_bind_group: wgpu::BindGroup,
+	fxaa_pipeline: wgpu::RenderPipeline,
+	fxaa_pipeline_layout: wgpu::PipelineLayout,
+	fxaa_bind_group_layout: wgpu::BindGroupLayout,
+	fxaa_sampler: wgpu::Sampler,
+	fxaa_target: Option<FxaaTarget>,
+	text: TextRenderer,
+	profiler: Option<GpuProfiler>,
+	adapter: wgpu::Adapter,
 	device: wgpu::Device,
 	queue: wgpu::Queue,
 	surface: wgpu::Surface<'static>,
 	surface_config: wgpu::SurfaceConfiguration,
+	// false while the window has no area or after the surface was lost and could not be reconfigured
+	surface_configured: bool,
+	surface_output: SurfaceOutput,
+	config: RendererConfig,
 	immediate: Immediate,
+	debug_view: DebugView,
+	last_frame_stats: FrameStats,
 	window: Arc<Window>,
 }
 
@@ -53,97 +481,796 @@ macro_rules! load_shader {
 }
 
 impl Renderer {
+	pub const MAX_CLIP_PLANES: usize = 4;
+	const OBJECT_ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+	const MAX_ANISOTROPY: u16 = 16;
+	const DEFAULT_SKY_TOP: [f32; 3] = [0.25, 0.45, 0.8];
+	const DEFAULT_SKY_BOTTOM: [f32; 3] = [0.75, 0.8, 0.85];
 
 	//public
 
-	pub async fn new(window: Window) -> anyhow::Result<Self> {
+	pub async fn new(window: Window, scene: &Scene, camera: &Camera, config: &RendererConfig) -> anyhow::Result<Self> {
+		Self::check_max_frame_latency(config.max_frame_latency)?;
+
 		let size = window.inner_size();
 
-		let immediate = Immediate::new(size.width, size.height);
+		let mut immediate = Immediate::new(size.width, size.height);
 
 		let window = Arc::new(window);
 
-		let instance = Self::create_instance();
+		let instance = Self::create_instance(config);
+
+		let surface = Self::create_surface(&instance, window.clone())?;
+
+		// the adapter is kept for later capability queries, it shares the instance
+		// context with the surface so it does not extend the instance lifetime
+		let adapter = Self::request_adapter(&instance, &surface, config).await?;
+
+		let (device, queue) = Self::request_device(&adapter).await?;
+
+		let (surface_config, surface_output) = Self::create_surface_config(&surface, &adapter, config, size.width, size.height)?;
+
+		// a window created minimized has no area, resize configures the surface once it has one
+		let surface_configured = 0 < size.width && 0 < size.height;
+		if surface_configured {
+			surface.configure(&device, &surface_config);
+		}
+
+		immediate.update_output_mode(surface_output);
+		immediate.update_tone_mapper(config.tone_mapper);
+		immediate.update_dither(Self::dither_surface(config, surface_config.format));
+
+		let scene_bind_group_layout = Self::create_scene_bind_group_layout(&device);
+		let scene_buffers = Self::create_validated(&device, config, "Scene Buffers", || SceneBuffers::new(&device, scene)).await?;
+		let material_texture = Self::create_default_material_texture(&device, &queue);
+		let anisotropy = Self::clamp_anisotropy(&adapter, config.anisotropy);
+		let material_sampler = Self::create_material_sampler(&device, config.texture_filter, anisotropy);
+		let scene_bind_group = Self::create_validated(&device, config, "Scene Bind Group", || Self::create_scene_bind_group(&device, &scene_bind_group_layout, &scene_buffers, &material_texture, &material_sampler)).await?;
+
+		immediate.update_scene_counts(scene);
+
+		let camera_bind_group_layout = Self::create_camera_bind_group_layout(&device);
+		let camera_buffer = Self::create_validated(&device, config, "Camera Buffer", || Self::create_camera_buffer(&device)).await?;
+		let globals_buffer = Self::create_validated(&device, config, "Globals Buffer", || Self::create_globals_buffer(&device)).await?;
+		let camera_bind_group = Self::create_validated(&device, config, "Camera Bind Group", || Self::create_camera_bind_group(&device, &camera_bind_group_layout, &camera_buffer, &globals_buffer)).await?;
+
+		queue.write_buffer(&camera_buffer, 0, bytemuck::bytes_of(&camera.uniform()));
+
+		let environment_bind_group_layout = Self::create_environment_bind_group_layout(&device);
+		let environment_sampler = Self::create_environment_sampler(&device);
+		let environment = Texture::cube_solid(&device, &queue, [0, 0, 0, 255]);
+		let sky_gradient_buffer = Self::create_validated(&device, config, "Sky Gradient Buffer", || Self::create_sky_gradient_buffer(&device)).await?;
+		let environment_bind_group = Self::create_validated(&device, config, "Environment Bind Group", || Self::create_environment_bind_group(&device, &environment_bind_group_layout, &environment, &environment_sampler, &sky_gradient_buffer)).await?;
+
+		queue.write_buffer(&sky_gradient_buffer, 0, bytemuck::bytes_of(&SkyGradient::new(Self::DEFAULT_SKY_TOP, Self::DEFAULT_SKY_BOTTOM)));
+
+		let overlay_bind_group_layout = Self::create_overlay_bind_group_layout(&device);
+		let grid = GridUniform::default();
+		let grid_buffer = Self::create_validated(&device, config, "Grid Buffer", || Self::create_grid_buffer(&device)).await?;
+		let clip_planes = [[0.0; 4]; Self::MAX_CLIP_PLANES];
+		let clip_plane_buffer = Self::create_validated(&device, config, "Clip Plane Buffer", || Self::create_clip_plane_buffer(&device)).await?;
+		let overlay_bind_group = Self::create_validated(&device, config, "Overlay Bind Group", || Self::create_overlay_bind_group(&device, &overlay_bind_group_layout, &grid_buffer, &clip_plane_buffer)).await?;
+
+		queue.write_buffer(&grid_buffer, 0, bytemuck::bytes_of(&grid));
+		queue.write_buffer(&clip_plane_buffer, 0, bytemuck::cast_slice(&clip_planes));
+
+		let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("Render Pipeline Layout"),
+			bind_group_layouts: &[&scene_bind_group_layout, &camera_bind_group_layout, &environment_bind_group_layout, &overlay_bind_group_layout],
+			immediate_size: size_of::<Immediate>().try_into()?,
+		});
+
+		let render_pipeline = Self::create_validated(&device, config, "Main Render Pipeline", || Self::create_render_pipeline(&device, &render_pipeline_layout, "fs_main", &[Self::color_target(Self::target_format(&surface_config))])).await?;
+
+		let fxaa_bind_group_layout = Self::create_fxaa_bind_group_layout(&device);
+		let fxaa_sampler = Self::create_fxaa_sampler(&device);
+		let fxaa_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("FXAA Pipeline Layout"),
+			bind_group_layouts: &[&fxaa_bind_group_layout],
+			immediate_size: size_of::<FxaaImmediate>().try_into()?,
+		});
+		let fxaa_pipeline = Self::create_validated(&device, config, "FXAA Pipeline", || Self::create_fxaa_pipeline(&device, &fxaa_pipeline_layout, Self::target_format(&surface_config))).await?;
+		let fxaa_target = if config.fxaa && surface_configured {
+			Some(Self::create_validated(&device, config, "FXAA Target", || Self::create_fxaa_target(&device, &fxaa_bind_group_layout, &fxaa_sampler, &surface_config)).await?)
+		} else {
+			None
+		};
+
+		let text = Self::create_validated(&device, config, "Text Renderer", || TextRenderer::new(&device, &queue, Self::target_format(&surface_config))).await?;
+
+		let renderer = Self {
+			render_pipeline,
+			render_pipeline_layout,
+			scene_bind_group_layout,
+			scene_bind_group,
+			scene_buffers,
+			material_texture,
+			material_sampler,
+			camera_buffer,
+			globals_buffer,
+			camera_bind_group_layout,
+			camera_bind_group,
+			camera: *camera,
+			stereo: None,
+			clock_start: Instant::now(),
+			frame_index: 0,
+			environment_bind_group_layout,
+			environment_sampler,
+			environment_bind_group,
+			sky_gradient_buffer,
+			grid,
+			grid_buffer,
+			clip_planes,
+			clip_plane_buffer,
+			overlay_bind_group,
+			fxaa_pipeline,
+			fxaa_pipeline_layout,
+			fxaa_bind_group_layout,
+			fxaa_sampler,
+			fxaa_target,
+			text,
+			profiler: None,
+			adapter,
+			device,
+			queue,
+			surface,
+			surface_config,
+			surface_configured,
+			surface_output,
+			config: RendererConfig {
+				anisotropy,
+				..config.clone()
+			},
+			immediate,
+			debug_view: DebugView::Shaded,
+			last_frame_stats: FrameStats::default(),
+			window,
+		};
+
+		if config.validate {
+			renderer.check_scene_upload(scene)?;
+		}
+
+		Ok(renderer)
+	}
+
+	pub fn dump_shaders(out_dir: &Path) -> anyhow::Result<()> {
+		let shaders = [
+			("vertex_shader", include_wesl!("vertex_shader")),
+			("fragment_shader", include_wesl!("fragment_shader")),
+			("text_shader", include_wesl!("text_shader")),
+			("fxaa_shader", include_wesl!("fxaa_shader")),
+		];
+		std::fs::create_dir_all(out_dir).with_context(|| format!("Failed to create shader dump directory {}", out_dir.display()))?;
+		for (name, source) in shaders {
+			let path = out_dir.join(name).with_extension("wgsl");
+			std::fs::write(&path, source).with_context(|| format!("Failed to write shader {}", path.display()))?;
+			log::info!("Wrote {}", path.display());
+		}
+		Ok(())
+	}
+
+	pub fn backend(&self) -> wgpu::Backend {
+		self.adapter.get_info().backend
+	}
+
+	pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+		self.adapter.get_info()
+	}
+
+	pub fn adapter_features(&self) -> wgpu::Features {
+		self.adapter.features()
+	}
+
+	pub fn surface_output(&self) -> SurfaceOutput {
+		self.surface_output
+	}
+
+	pub fn last_frame_stats(&self) -> &FrameStats {
+		&self.last_frame_stats
+	}
+
+	pub fn profiling(&self) -> bool {
+		self.profiler.is_some()
+	}
+
+	pub fn set_profiling(&mut self, enabled: bool) -> anyhow::Result<()> {
+		if enabled && !self.device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+			bail!("{} does not support GPU timestamp queries", self.adapter.get_info().name);
+		}
+		self.profiler = enabled.then(|| GpuProfiler::new(&self.device, &self.queue));
+		Ok(())
+	}
+
+	// GPU time of each pass of the last profiled frame, the text overlay is part of the last pass
+	pub fn pass_timings(&self) -> &[(&'static str, Duration)] {
+		self.profiler.as_ref().map(|profiler| profiler.timings()).unwrap_or_default()
+	}
+
+	// span of the last timed frame on the GPU, to compare with the sum of pass_timings
+	pub fn gpu_frame_time(&self) -> Option<Duration> {
+		self.profiler.as_ref().and_then(|profiler| profiler.frame_time())
+	}
+
+	// None on backends that do not expose their allocator, such as GL
+	pub fn memory_report(&self) -> Option<MemoryReport> {
+		let report = self.device.generate_allocator_report()?;
+		Some(MemoryReport {
+			allocated_bytes: report.total_allocated_bytes,
+			reserved_bytes: report.total_reserved_bytes,
+			allocations: report.allocations.len(),
+			blocks: report.blocks.len(),
+		})
+	}
+
+    pub fn resize(&mut self) {
+		let size = self.window.inner_size();
+		if 0 < size.width && 0 < size.height {
+			self.surface_config.width = size.width;
+			self.surface_config.height = size.height;
+			self.surface.configure(&self.device, &self.surface_config);
+			self.surface_configured = true;
+			self.immediate.update_window_size(size.width, size.height);
+			self.rebuild_fxaa_target();
+		}
+    }
+
+	// pipelines are created with the renderer, so only the surface can be missing
+	pub fn is_ready(&self) -> bool {
+		self.surface_configured
+	}
+
+	pub fn window(&self) -> &Window {
+		&self.window
+	}
+
+	pub fn set_title(&self, title: &str) {
+		self.window.set_title(title);
+	}
+
+	pub fn request_redraw(&self) {
+		self.window.request_redraw();
+	}
+
+	pub fn set_camera(&mut self, camera: &Camera) {
+		self.camera = *camera;
+		self.queue.write_buffer(&self.camera_buffer, 0, bytemuck::bytes_of(&camera.uniform()));
+		self.write_stereo_cameras();
+		self.window.request_redraw();
+	}
+
+	pub fn stereo_ipd(&self) -> Option<f32> {
+		self.stereo.as_ref().map(|stereo| stereo.ipd)
+	}
+
+	// renders the left and right eye into the two halves of the surface, None renders a single view
+	pub fn set_stereo(&mut self, ipd: Option<f32>) {
+		self.stereo = ipd.map(|ipd| {
+			let left_buffer = Self::create_camera_buffer(&self.device);
+			let right_buffer = Self::create_camera_buffer(&self.device);
+			StereoEyes {
+				ipd,
+				left_bind_group: Self::create_camera_bind_group(&self.device, &self.camera_bind_group_layout, &left_buffer, &self.globals_buffer),
+				right_bind_group: Self::create_camera_bind_group(&self.device, &self.camera_bind_group_layout, &right_buffer, &self.globals_buffer),
+				left_buffer,
+				right_buffer,
+			}
+		});
+		self.write_stereo_cameras();
+		self.window.request_redraw();
+	}
+
+	// queued for the next rendered frame only, x and y are in pixels from the top left
+	pub fn draw_text(&mut self, text: &str, x: f32, y: f32, scale: f32, color: [f32; 4]) {
+		self.text.queue_text(text, x, y, scale, color);
+	}
+
+	// replaces every scene buffer, the camera is left as is. The previous scene is
+	// unloaded first so both never coexist, a failed upload leaves the scene empty
+	pub fn set_scene(&mut self, scene: &Scene) -> anyhow::Result<()> {
+		self.unload_scene();
+		self.scene_buffers = self.create_checked("Scene Buffers", || SceneBuffers::new(&self.device, scene))?;
+		self.rebuild_scene_bind_group();
+		self.immediate.update_scene_counts(scene);
+		if self.config.validate {
+			self.check_scene_upload(scene)?;
+		}
+		self.window.request_redraw();
+		Ok(())
+	}
+
+	// releases the scene buffers, the old bind group is replaced too since it keeps them alive
+	pub fn unload_scene(&mut self) {
+		let empty = Scene::new(0);
+		self.scene_buffers = SceneBuffers::new(&self.device, &empty);
+		self.rebuild_scene_bind_group();
+		self.immediate.update_scene_counts(&empty);
+		// object ids only make sense for the scene they were picked in
+		self.immediate.update_solo_object(None);
+		self.window.request_redraw();
+	}
+
+	pub fn solo_object(&self) -> Option<u32> {
+		(self.immediate.solo_object != 0).then_some(self.immediate.solo_object)
+	}
+
+	// traces only the primitive with this object id, see Primitive::object_id. Everything else is
+	// skipped by the rays as if it was not in the scene, None brings the whole scene back
+	pub fn solo(&mut self, object_id: Option<u32>) {
+		self.immediate.update_solo_object(object_id.filter(|&id| id != 0));
+		self.window.request_redraw();
+	}
+
+	pub fn reset_clock(&mut self) {
+		self.clock_start = Instant::now();
+		self.frame_index = 0;
+	}
+
+	pub fn set_cubemap(&mut self, faces: [&Path; Texture::CUBE_FACES]) -> anyhow::Result<()> {
+		let environment = Texture::cube_from_files(&self.device, &self.queue, faces)?;
+		self.environment_bind_group = Self::create_environment_bind_group(&self.device, &self.environment_bind_group_layout, &environment, &self.environment_sampler, &self.sky_gradient_buffer);
+		self.immediate.update_has_environment(true);
+		self.window.request_redraw();
+		Ok(())
+	}
+
+	// background shown while no environment map is set
+	pub fn set_sky_gradient(&mut self, top: [f32; 3], bottom: [f32; 3]) {
+		self.queue.write_buffer(&self.sky_gradient_buffer, 0, bytemuck::bytes_of(&SkyGradient::new(top, bottom)));
+		self.window.request_redraw();
+	}
+
+	pub fn grid_enabled(&self) -> bool {
+		self.grid.enabled != 0
+	}
+
+	// ground grid on y = 0, drawn over the scene wherever it is in front of the hit surface
+	pub fn set_grid(&mut self, enabled: bool) {
+		self.grid.enabled = enabled.into();
+		self.queue.write_buffer(&self.grid_buffer, 0, bytemuck::bytes_of(&self.grid));
+		self.window.request_redraw();
+	}
+
+	pub fn set_grid_style(&mut self, spacing: f32, color: [f32; 3]) {
+		self.grid.spacing = spacing.max(f32::EPSILON);
+		self.grid.color = color;
+		self.queue.write_buffer(&self.grid_buffer, 0, bytemuck::bytes_of(&self.grid));
+		self.window.request_redraw();
+	}
+
+	// geometry on the side the normal points to is cut away, None removes the plane
+	pub fn set_clip_plane(&mut self, index: usize, plane: Option<(Vec3, f32)>) -> anyhow::Result<()> {
+		if Self::MAX_CLIP_PLANES <= index {
+			bail!("Clip plane index {} out of range (max {} planes)", index, Self::MAX_CLIP_PLANES);
+		}
+		self.clip_planes[index] = match plane {
+			Some((normal, offset)) => normal.normalize_or(Vec3::Y).extend(offset).to_array(),
+			None => [0.0; 4],
+		};
+		self.queue.write_buffer(&self.clip_plane_buffer, 0, bytemuck::cast_slice(&self.clip_planes));
+		self.window.request_redraw();
+		Ok(())
+	}
+
+	pub fn dither_enabled(&self) -> bool {
+		self.immediate.dither != 0
+	}
+
+	// overrides the default of dithering 8 bit surfaces only
+	pub fn set_dither(&mut self, enabled: bool) {
+		self.config.dither = Some(enabled);
+		self.immediate.update_dither(enabled);
+		self.window.request_redraw();
+	}
+
+	pub fn fxaa_enabled(&self) -> bool {
+		self.config.fxaa
+	}
+
+	pub fn set_fxaa(&mut self, enabled: bool) {
+		self.config.fxaa = enabled;
+		self.rebuild_fxaa_target();
+		self.window.request_redraw();
+	}
+
+	pub fn anisotropy(&self) -> u16 {
+		self.config.anisotropy
+	}
+
+	// levels above what the adapter supports are clamped
+	pub fn set_anisotropy(&mut self, level: u16) {
+		self.config.anisotropy = Self::clamp_anisotropy(&self.adapter, level);
+		self.material_sampler = Self::create_material_sampler(&self.device, self.config.texture_filter, self.config.anisotropy);
+		self.rebuild_scene_bind_group();
+		self.window.request_redraw();
+	}
+
+	pub fn tone_mapper(&self) -> ToneMapper {
+		self.config.tone_mapper
+	}
+
+	pub fn set_tone_mapper(&mut self, tone_mapper: ToneMapper) {
+		self.config.tone_mapper = tone_mapper;
+		self.immediate.update_tone_mapper(tone_mapper);
+		self.window.request_redraw();
+	}
+
+	pub fn debug_view(&self) -> DebugView {
+		self.debug_view
+	}
+
+	pub fn set_debug_view(&mut self, debug_view: DebugView) {
+		self.debug_view = debug_view;
+		self.immediate.update_debug_view(debug_view);
+		self.window.request_redraw();
+	}
+
+	pub fn texture_filter(&self) -> wgpu::FilterMode {
+		self.config.texture_filter
+	}
+
+	pub fn set_texture_filter(&mut self, filter: wgpu::FilterMode) {
+		self.config.texture_filter = filter;
+		self.material_sampler = Self::create_material_sampler(&self.device, self.config.texture_filter, self.config.anisotropy);
+		self.rebuild_scene_bind_group();
+		self.window.request_redraw();
+	}
+
+	// debug only: stalls until the GPU is idle, the buffer needs COPY_SRC usage
+	pub fn read_buffer<T: bytemuck::Pod>(&self, buffer: &wgpu::Buffer) -> anyhow::Result<Vec<T>> {
+		let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Readback Staging Buffer"),
+			size: buffer.size(),
+			usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+
+		let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Readback Command Encoder"),
+		});
+		encoder.copy_buffer_to_buffer(buffer, 0, &staging_buffer, 0, buffer.size());
+		self.queue.submit(std::iter::once(encoder.finish()));
+
+		let staging_slice = staging_buffer.slice(..);
+		let (sender, receiver) = std::sync::mpsc::channel();
+		staging_slice.map_async(wgpu::MapMode::Read, move |result| {
+			let _ = sender.send(result);
+		});
+		self.device.poll(wgpu::PollType::wait_indefinitely()).context("Failed to wait for buffer readback")?;
+		receiver.recv().context("Buffer map callback was dropped")?.context("Failed to map readback buffer")?;
+
+		let data = bytemuck::pod_collect_to_vec(&staging_slice.get_mapped_range());
+		staging_buffer.unmap();
+		Ok(data)
+	}
+
+	pub fn reconfigure_surface(&mut self) -> anyhow::Result<()> {
+		let size = self.window.inner_size();
+		if size.width == 0 || size.height == 0 {
+			return Ok(());
+		}
+		let (surface_config, surface_output) = Self::create_surface_config(&self.surface, &self.adapter, &self.config, size.width, size.height)?;
+		if Self::target_format(&surface_config) != Self::target_format(&self.surface_config) {
+			log::info!("Surface format changed from {:?} to {:?}", self.surface_config.format, surface_config.format);
+			self.render_pipeline = self.create_checked("Main Render Pipeline", || Self::create_render_pipeline(&self.device, &self.render_pipeline_layout, "fs_main", &[Self::color_target(Self::target_format(&surface_config))]))?;
+			self.fxaa_pipeline = self.create_checked("FXAA Pipeline", || Self::create_fxaa_pipeline(&self.device, &self.fxaa_pipeline_layout, Self::target_format(&surface_config)))?;
+			self.text.set_target_format(&self.device, Self::target_format(&surface_config));
+		}
+		self.surface.configure(&self.device, &surface_config);
+		self.surface_configured = true;
+		self.surface_config = surface_config;
+		self.surface_output = surface_output;
+		self.immediate.update_window_size(size.width, size.height);
+		self.immediate.update_output_mode(surface_output);
+		self.immediate.update_dither(Self::dither_surface(&self.config, self.surface_config.format));
+		self.rebuild_fxaa_target();
+		Ok(())
+	}
+    
+    pub fn render(&mut self) {
+		let frame_start = Instant::now();
+		let mut stats = FrameStats::default();
+		self.last_frame_stats = FrameStats::default();
+
+		// text is queued for every redraw, skipped frames must not let it pile up
+		let Some(frame) = self.acquire_frame() else {
+			self.text.clear_queue();
+			return;
+		};
+
+		let view = frame.texture.create_view(&wgpu::TextureViewDescriptor {
+			label: Some("Render Texture View"),
+			format: Some(Self::target_format(&self.surface_config)),
+			..Default::default()
+		});
+
+		self.update_globals();
+		self.text.prepare(&self.device, &self.queue);
+
+		let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Render Command Encoder"),
+		});
+
+		let profile = self.profiler.as_ref().is_some_and(|profiler| profiler.sample_due());
+		self.encode_main_pass(&mut encoder, &view, &mut stats, profile);
+		let timed_passes = self.timed_passes();
+		if let Some(profiler) = self.profiler.as_ref().filter(|_| profile) {
+			profiler.resolve(&mut encoder, timed_passes.len() as u32);
+		}
+
+		self.queue.submit(std::iter::once(encoder.finish()));
+		self.window.pre_present_notify();
+		frame.present();
+
+		if let Some(profiler) = self.profiler.as_mut().filter(|_| profile) && let Err(e) = profiler.read(&self.device, timed_passes) {
+			log::warn!("Failed to read GPU timestamps: {:?}", e);
+		}
+		stats.gpu_time = self.profiler.as_ref().and_then(|profiler| profiler.total());
+
+		if self.config.device_polling == DevicePolling::PerFrame && let Err(e) = self.device.poll(wgpu::PollType::Poll) {
+			log::warn!("Failed to poll the device: {:?}", e);
+		}
+
+		stats.cpu_time = frame_start.elapsed();
+		stats.presented = true;
+		self.last_frame_stats = stats;
+    }
+
+	// renders the current frame offscreen and saves it, only SDR 8 bit surfaces are supported
+	pub fn capture_frame(&self, path: &Path) -> anyhow::Result<()> {
+		let image = self.capture_image()?;
+		image.save(path).with_context(|| format!("Failed to save frame capture {}", path.display()))
+	}
+
+	pub fn capture_image(&self) -> anyhow::Result<image::RgbaImage> {
+		let format = Self::target_format(&self.surface_config);
+		let bgra = Self::capture_is_bgra(format)?;
+		let texture = self.create_capture_texture(format);
+		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+		let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Capture Command Encoder"),
+		});
+		self.encode_main_pass(&mut encoder, &view, &mut FrameStats::default(), false);
+		self.queue.submit(std::iter::once(encoder.finish()));
+
+		self.read_rgba_image(&texture, bgra)
+	}
+
+	// renders a frame of any size in tiles of at most tile_size pixels, so it may exceed the
+	// texture size limit. Tiles are plain scene passes without FXAA or text, so they need no overlap
+	pub fn capture_tiled(&self, width: u32, height: u32, tile_size: u32) -> anyhow::Result<image::RgbaImage> {
+		let max_tile_size = self.device.limits().max_texture_dimension_2d;
+		if tile_size == 0 || max_tile_size < tile_size {
+			bail!("Tile size {} must be between 1 and {}", tile_size, max_tile_size);
+		}
+		if self.stereo.is_some() {
+			bail!("Tiled capture does not support the stereo preview");
+		}
+		let format = Self::target_format(&self.surface_config);
+		let bgra = Self::capture_is_bgra(format)?;
+		let texture = self.create_target_texture(format, tile_size.min(width), tile_size.min(height));
+		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+		let mut image = image::RgbaImage::new(width, height);
+		for y in (0..height).step_by(tile_size as usize) {
+			for x in (0..width).step_by(tile_size as usize) {
+				let mut immediate = self.immediate;
+				immediate.update_tile(x, y, width, height);
+				let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+					label: Some("Tile Command Encoder"),
+				});
+				drop(self.begin_scene_pass(&mut encoder, &[&view], &self.render_pipeline, &immediate, None));
+				self.queue.submit(std::iter::once(encoder.finish()));
+
+				// edge tiles are rendered whole, replace clips them to the image
+				let tile = self.read_rgba_image(&texture, bgra)?;
+				image::imageops::replace(&mut image, &tile, x.into(), y.into());
+			}
+			log::info!("Rendered tile rows up to {} of {}", (y + tile_size).min(height), height);
+		}
+		Ok(image)
+	}
+
+	// saves the hit distance of every pixel as the DepthHeatmap view, without FXAA or the text overlay
+	pub fn capture_depth(&self, path: &Path) -> anyhow::Result<()> {
+		let format = Self::target_format(&self.surface_config);
+		let bgra = Self::capture_is_bgra(format)?;
+		let texture = self.create_capture_texture(format);
+		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+		let mut immediate = self.immediate;
+		immediate.update_debug_view(DebugView::DepthHeatmap);
+		immediate.update_dither(false);
+		let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Depth Capture Command Encoder"),
+		});
+		drop(self.begin_scene_pass(&mut encoder, &[&view], &self.render_pipeline, &immediate, None));
+		self.queue.submit(std::iter::once(encoder.finish()));
+
+		let image = self.read_rgba_image(&texture, bgra)?;
+		image.save(path).with_context(|| format!("Failed to save depth capture {}", path.display()))
+	}
+
+	// linear scene radiance before tone mapping and output encoding, without the text overlay
+	pub fn capture_frame_exr(&self, path: &Path) -> anyhow::Result<()> {
+		let format = wgpu::TextureFormat::Rgba32Float;
+		let pipeline = self.create_checked("Linear Capture Pipeline", || Self::create_render_pipeline(&self.device, &self.render_pipeline_layout, "fs_main", &[Self::color_target(format)]))?;
+		let mut immediate = self.immediate;
+		immediate.update_output_mode(SurfaceOutput::Sdr);
+		immediate.update_tone_mapper(ToneMapper::None);
+		immediate.update_dither(false);
+
+		let texture = self.create_capture_texture(format);
+		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+		let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Linear Capture Command Encoder"),
+		});
+		drop(self.begin_scene_pass(&mut encoder, &[&view], &pipeline, &immediate, None));
+		self.queue.submit(std::iter::once(encoder.finish()));
 
-		let surface = Self::create_surface(&instance, window.clone())?;
+		let pixels: Vec<f32> = bytemuck::pod_collect_to_vec(&self.read_texture(&texture, 16)?);
+		let image = image::Rgba32FImage::from_raw(texture.width(), texture.height(), pixels).context("Capture buffer does not match the frame size")?;
+		image.save_with_format(path, image::ImageFormat::OpenExr).with_context(|| format!("Failed to save frame capture {}", path.display()))
+	}
 
-		let adapter = Self::request_adapter(&instance, &surface).await?;
+	// renders the shaded frame and the id of the primitive under every pixel into two
+	// targets, returns the ids row by row, see Primitive::from_object_id
+	pub fn capture_object_ids(&self) -> anyhow::Result<Vec<u32>> {
+		let color_format = Self::target_format(&self.surface_config);
+		let targets = [Self::color_target(color_format), Self::color_target(Self::OBJECT_ID_FORMAT)];
+		let pipeline = self.create_checked("Object Id Pipeline", || Self::create_render_pipeline(&self.device, &self.render_pipeline_layout, "fs_object_ids", &targets))?;
 
-		let (device, queue) = Self::request_device(&adapter).await?;
+		let color_texture = self.create_capture_texture(color_format);
+		let color_view = color_texture.create_view(&wgpu::TextureViewDescriptor::default());
+		let object_id_texture = self.create_capture_texture(Self::OBJECT_ID_FORMAT);
+		let object_id_view = object_id_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-		let surface_caps = surface.get_capabilities(&adapter);
-		let surface_format = Self::find_surface_format(&surface_caps)?;
-		let alpha_mode = Self::find_alpha_mode(&surface_caps)?;
+		let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Object Id Command Encoder"),
+		});
+		drop(self.begin_scene_pass(&mut encoder, &[&color_view, &object_id_view], &pipeline, &self.immediate, None));
+		self.queue.submit(std::iter::once(encoder.finish()));
 
-		let surface_config = wgpu::SurfaceConfiguration {
-			usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-			format: surface_format,
-			width: size.width,
-			height: size.height,
-			present_mode: wgpu::PresentMode::AutoVsync,
-			desired_maximum_frame_latency: 2,
-			alpha_mode: alpha_mode,
-			view_formats: vec![surface_format.add_srgb_suffix()],
-		};
+		Ok(bytemuck::pod_collect_to_vec(&self.read_texture(&object_id_texture, 4)?))
+	}
 
-		surface.configure(&device, &surface_config);
+	// debug only: a full offscreen render and readback per call, pixel is from the top left
+	pub fn object_at(&self, pixel: [u32; 2]) -> anyhow::Result<Option<Primitive>> {
+		let [x, y] = pixel;
+		if self.surface_config.width <= x || self.surface_config.height <= y {
+			bail!("Pixel {}x{} is outside the {}x{} surface", x, y, self.surface_config.width, self.surface_config.height);
+		}
+		let object_ids = self.capture_object_ids()?;
+		let object_id = object_ids[(y * self.surface_config.width + x) as usize];
+		Ok(Primitive::from_object_id(object_id, self.immediate.sphere_count as usize))
+	}
 
-		let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-			label: Some("Render Pipeline Layout"),
-			bind_group_layouts: &[],
-			immediate_size: size_of::<Immediate>().try_into()?,
-		});
+	//private
 
-		let render_pipeline = Self::create_render_pipeline(&device, render_pipeline_layout, &surface_config);
+	fn write_stereo_cameras(&self) {
+		if let Some(stereo) = &self.stereo {
+			self.queue.write_buffer(&stereo.left_buffer, 0, bytemuck::bytes_of(&self.camera.eye_uniform(-0.5 * stereo.ipd)));
+			self.queue.write_buffer(&stereo.right_buffer, 0, bytemuck::bytes_of(&self.camera.eye_uniform(0.5 * stereo.ipd)));
+		}
+	}
 
-		Ok(Self {
-			render_pipeline,
-			device,
-			queue,
-			surface,
-			surface_config,
-			immediate,
-			window,
-		})
+	fn update_globals(&mut self) {
+		let globals = Globals {
+			time: self.clock_start.elapsed().as_secs_f32(),
+			frame_index: self.frame_index,
+			resolution: [self.surface_config.width as f32, self.surface_config.height as f32],
+		};
+		self.queue.write_buffer(&self.globals_buffer, 0, bytemuck::bytes_of(&globals));
+		self.frame_index = self.frame_index.wrapping_add(1);
 	}
 
-    pub fn resize(&mut self) {
-		let size = self.window.inner_size();
-		if 0 < size.width && 0 < size.height {
-			self.surface_config.width = size.width;
-			self.surface_config.height = size.height;
-			self.surface.configure(&self.device, &self.surface_config);
-			self.immediate.update_window_size(size.width, size.height);
+	// None when nothing can be drawn this frame, lost or outdated surfaces are configured again
+	fn acquire_frame(&mut self) -> Option<wgpu::SurfaceTexture> {
+		if !self.is_ready() {
+			return None;
 		}
-    }
-    
-    pub fn render(&mut self) {
-		let frame = match self.surface.get_current_texture() {
-			Ok(frame) => frame,
-			Err(wgpu::SurfaceError::Outdated) | Err(wgpu::SurfaceError::Lost) => {
+		match self.surface.get_current_texture() {
+			Ok(frame) => Some(frame),
+			Err(wgpu::SurfaceError::Outdated) => {
 				self.resize();
-				return;
+				None
+			},
+			Err(wgpu::SurfaceError::Lost) => {
+				self.surface_configured = false;
+				if let Err(e) = self.reconfigure_surface() {
+					log::error!("Failed to reconfigure lost surface: {:?}", e);
+				}
+				None
 			},
 			Err(e) => {
 				log::error!("Failed to acquire next swap chain texture: {:?}", e);
-				return;
+				None
+			},
+		}
+	}
+
+	// names of the passes encode_main_pass writes timestamps for, in slot order
+	fn timed_passes(&self) -> &'static [&'static str] {
+		if self.fxaa_target.is_some() { &["Scene", "FXAA"] } else { &["Scene"] }
+	}
+
+	fn encode_main_pass(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, stats: &mut FrameStats, profile: bool) {
+		let scene_draws = if self.stereo.is_some() { 2 } else { 1 };
+		stats.draw_calls += scene_draws;
+		let timestamp_writes = |slot| self.profiler.as_ref().filter(|_| profile).map(|profiler| profiler.timestamp_writes(slot));
+		let mut render_pass = match &self.fxaa_target {
+			Some(fxaa_target) => {
+				drop(self.begin_scene_pass(encoder, &[&fxaa_target.view], &self.render_pipeline, &self.immediate, timestamp_writes(0)));
+				stats.draw_calls += 1;
+				self.begin_fxaa_pass(encoder, view, fxaa_target, timestamp_writes(1))
 			},
+			None => self.begin_scene_pass(encoder, &[view], &self.render_pipeline, &self.immediate, timestamp_writes(0)),
 		};
 
-		let view = frame.texture.create_view(&wgpu::TextureViewDescriptor {
-			label: Some("Render Texture View"),
-			format: Some(self.surface_config.format.add_srgb_suffix()),
-			..Default::default()
-		});
+		// text is drawn over the final image, it bypasses tone mapping and the HDR encode
+		let glyphs = self.text.draw(&mut render_pass, [self.surface_config.width as f32, self.surface_config.height as f32]);
+		if 0 < glyphs {
+			stats.draw_calls += 1;
+		}
+	}
 
-		let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-			label: Some("Render Command Encoder"),
+	// begins a pass on `views` and ray casts the scene into them with the given pipeline,
+	// one view per pipeline target in the same order
+	fn begin_scene_pass<'a>(&self, encoder: &'a mut wgpu::CommandEncoder, views: &[&wgpu::TextureView], pipeline: &wgpu::RenderPipeline, immediate: &Immediate, timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'_>>) -> wgpu::RenderPass<'a> {
+		let color_attachments: Vec<_> = views.iter().map(|view| Some(wgpu::RenderPassColorAttachment {
+			view,
+			depth_slice: None,
+			resolve_target: None,
+			ops: wgpu::Operations {
+				load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+				store: wgpu::StoreOp::Store,
+			},
+		})).collect();
+		let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			label: Some("Render Pass"),
+			color_attachments: &color_attachments,
+			depth_stencil_attachment: None,
+			timestamp_writes,
+			occlusion_query_set: None,
+			multiview_mask: None,
 		});
 
+		render_pass.set_pipeline(pipeline);
+		render_pass.set_bind_group(0, &self.scene_bind_group, &[]);
+		render_pass.set_bind_group(2, &self.environment_bind_group, &[]);
+		render_pass.set_bind_group(3, &self.overlay_bind_group, &[]);
+		match &self.stereo {
+			Some(stereo) => {
+				let [width, height] = immediate.window_size;
+				let eye_width = width / 2;
+				for (x, camera_bind_group) in [(0, &stereo.left_bind_group), (eye_width, &stereo.right_bind_group)] {
+					let mut eye = *immediate;
+					eye.update_viewport(x, 0, eye_width, height);
+					render_pass.set_viewport(x as f32, 0.0, eye_width as f32, height as f32, 0.0, 1.0);
+					render_pass.set_bind_group(1, camera_bind_group, &[]);
+					render_pass.set_immediates(0, bytemuck::bytes_of(&eye));
+					render_pass.draw(0..3, 0..1);
+				}
+				// later draws in the pass such as the text overlay cover the whole target again
+				render_pass.set_viewport(0.0, 0.0, width as f32, height as f32, 0.0, 1.0);
+			},
+			None => {
+				render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+				render_pass.set_immediates(0, bytemuck::bytes_of(immediate));
+				render_pass.draw(0..3, 0..1);
+			},
+		}
+		render_pass
+	}
+
+	// begins a pass on `view` and resolves the FXAA target into it
+	fn begin_fxaa_pass<'a>(&self, encoder: &'a mut wgpu::CommandEncoder, view: &wgpu::TextureView, fxaa_target: &FxaaTarget, timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'_>>) -> wgpu::RenderPass<'a> {
 		let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-			label: Some("Render Pass"),
+			label: Some("FXAA Pass"),
 			color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-				view: &view,
+				view,
 				depth_slice: None,
 				resolve_target: None,
 				ops: wgpu::Operations {
@@ -152,47 +1279,136 @@ impl Renderer {
 				},
 			})],
 			depth_stencil_attachment: None,
-			timestamp_writes: None,
+			timestamp_writes,
 			occlusion_query_set: None,
 			multiview_mask: None,
 		});
 
-		render_pass.set_pipeline(&self.render_pipeline);
-		render_pass.set_immediates(0, bytemuck::bytes_of(&self.immediate));
+		render_pass.set_pipeline(&self.fxaa_pipeline);
+		render_pass.set_bind_group(0, &fxaa_target.bind_group, &[]);
+		render_pass.set_immediates(0, bytemuck::bytes_of(&FxaaImmediate::default()));
 		render_pass.draw(0..3, 0..1);
+		render_pass
+	}
+
+	fn create_capture_texture(&self, format: wgpu::TextureFormat) -> wgpu::Texture {
+		self.create_target_texture(format, self.surface_config.width, self.surface_config.height)
+	}
+
+	fn create_target_texture(&self, format: wgpu::TextureFormat, width: u32, height: u32) -> wgpu::Texture {
+		self.device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("Capture Texture"),
+			size: wgpu::Extent3d {
+				width,
+				height,
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format,
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+			view_formats: &[],
+		})
+	}
+
+	fn capture_is_bgra(format: wgpu::TextureFormat) -> anyhow::Result<bool> {
+		match format {
+			wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => Ok(false),
+			wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => Ok(true),
+			_ => bail!("Frame capture does not support the {:?} surface format", format),
+		}
+	}
+
+	fn read_rgba_image(&self, texture: &wgpu::Texture, bgra: bool) -> anyhow::Result<image::RgbaImage> {
+		let mut pixels = self.read_texture(texture, 4)?;
+		for pixel in pixels.chunks_exact_mut(4) {
+			if bgra {
+				pixel.swap(0, 2);
+			}
+			pixel[3] = 255;
+		}
+		image::RgbaImage::from_raw(texture.width(), texture.height(), pixels).context("Capture buffer does not match the frame size")
+	}
 
-		drop(render_pass);
+	// debug only: stalls until the GPU is idle, returns tightly packed rows
+	fn read_texture(&self, texture: &wgpu::Texture, bytes_per_pixel: u32) -> anyhow::Result<Vec<u8>> {
+		// copied rows must be padded to the copy alignment
+		let unpadded_bytes_per_row = bytes_per_pixel * texture.width();
+		let bytes_per_row = unpadded_bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+		let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Texture Readback Staging Buffer"),
+			size: (bytes_per_row * texture.height()) as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
 
+		let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Texture Readback Command Encoder"),
+		});
+		encoder.copy_texture_to_buffer(
+			texture.as_image_copy(),
+			wgpu::TexelCopyBufferInfo {
+				buffer: &staging_buffer,
+				layout: wgpu::TexelCopyBufferLayout {
+					offset: 0,
+					bytes_per_row: Some(bytes_per_row),
+					rows_per_image: Some(texture.height()),
+				},
+			},
+			texture.size(),
+		);
 		self.queue.submit(std::iter::once(encoder.finish()));
-		self.window.pre_present_notify();
-		frame.present();
-    }
 
-	//private
+		let staging_slice = staging_buffer.slice(..);
+		let (sender, receiver) = std::sync::mpsc::channel();
+		staging_slice.map_async(wgpu::MapMode::Read, move |result| {
+			let _ = sender.send(result);
+		});
+		self.device.poll(wgpu::PollType::wait_indefinitely()).context("Failed to wait for texture readback")?;
+		receiver.recv().context("Buffer map callback was dropped")?.context("Failed to map readback buffer")?;
+
+		let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * texture.height()) as usize);
+		for row in staging_slice.get_mapped_range().chunks_exact(bytes_per_row as usize) {
+			pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+		}
+		staging_buffer.unmap();
+		Ok(pixels)
+	}
 
-	fn create_instance() -> wgpu::Instance {
-		wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default())
+	fn create_instance(config: &RendererConfig) -> wgpu::Instance {
+		let descriptor = wgpu::InstanceDescriptor::from_env_or_default();
+		wgpu::Instance::new(&wgpu::InstanceDescriptor {
+			backends: config.backends,
+			flags: if config.validate { wgpu::InstanceFlags::debugging() } else { descriptor.flags },
+			..descriptor
+		})
 	}
 
 	fn create_surface(instance: &wgpu::Instance, window: Arc<Window>) -> anyhow::Result<wgpu::Surface<'static>> {
 		instance.create_surface(window).context("Failed to create wgpu surface")
 	}
 
-	async fn request_adapter(instance: &wgpu::Instance, surface: &wgpu::Surface<'_>) -> anyhow::Result<wgpu::Adapter> {
-		instance.request_adapter(
+	async fn request_adapter(instance: &wgpu::Instance, surface: &wgpu::Surface<'_>, config: &RendererConfig) -> anyhow::Result<wgpu::Adapter> {
+		let adapter = instance.request_adapter(
 			&wgpu::RequestAdapterOptions {
 				power_preference: wgpu::PowerPreference::from_env().unwrap_or(wgpu::PowerPreference::HighPerformance),
 				force_fallback_adapter: false,
 				compatible_surface: Some(surface),
 			},
-		).await.context("Failed to request wgpu adapter")
+		).await;
+		if adapter.is_err() {
+			log::error!("No adapter found, attempted backends: {:?}", config.enabled_backends().collect::<Vec<_>>());
+		}
+		adapter.context("Failed to request wgpu adapter")
 	}
 
 	async fn request_device(adapter: &wgpu::Adapter) -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
 		adapter.request_device(
 			&wgpu::DeviceDescriptor {
 				label: Some("Renderer Device"),
-				required_features: wgpu::Features::IMMEDIATES,
+				// timestamps are optional, only the profiler overlay needs them
+				required_features: wgpu::Features::IMMEDIATES | (adapter.features() & wgpu::Features::TIMESTAMP_QUERY),
 				required_limits: wgpu::Limits {
 					max_immediate_size: size_of::<Immediate>().try_into()?,
 					..wgpu::Limits::default()
@@ -204,8 +1420,99 @@ impl Renderer {
 		).await.context("Failed to request wgpu device")
 	}
 
-	fn find_surface_format(surface_caps: &wgpu::SurfaceCapabilities) -> anyhow::Result<wgpu::TextureFormat> {
-		surface_caps.formats.first().copied().ok_or(anyhow!("No supported surface formats found (surface is incompatible with adapter)"))
+	async fn create_validated<T>(device: &wgpu::Device, config: &RendererConfig, label: &str, create: impl FnOnce() -> T) -> anyhow::Result<T> {
+		if !config.validate {
+			return Ok(create());
+		}
+		device.push_error_scope(wgpu::ErrorFilter::Validation);
+		let resource = create();
+		match device.pop_error_scope().await {
+			Some(error) => Err(anyhow!("Validation failed while creating {}: {}", label, error)),
+			None => Ok(resource),
+		}
+	}
+
+	// create_validated for calls from the event loop, which must never block on wasm. Native
+	// error scopes resolve right away, a scope still pending on the web is dropped unchecked
+	fn create_checked<T>(&self, label: &str, create: impl FnOnce() -> T) -> anyhow::Result<T> {
+		if !self.config.validate {
+			return Ok(create());
+		}
+		self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+		let resource = create();
+		let mut pop = std::pin::pin!(self.device.pop_error_scope());
+		match pop.as_mut().poll(&mut std::task::Context::from_waker(std::task::Waker::noop())) {
+			Poll::Ready(Some(error)) => Err(anyhow!("Validation failed while creating {}: {}", label, error)),
+			Poll::Ready(None) => Ok(resource),
+			Poll::Pending => {
+				log::debug!("Validation of {} has not resolved yet, skipped", label);
+				Ok(resource)
+			},
+		}
+	}
+
+	fn create_surface_config(surface: &wgpu::Surface<'_>, adapter: &wgpu::Adapter, config: &RendererConfig, width: u32, height: u32) -> anyhow::Result<(wgpu::SurfaceConfiguration, SurfaceOutput)> {
+		let surface_caps = surface.get_capabilities(adapter);
+		let (surface_format, surface_output) = Self::find_surface_format(&surface_caps, config.hdr)?;
+		let alpha_mode = Self::find_alpha_mode(&surface_caps)?;
+
+		// HDR formats are written as is, SDR goes through an sRGB view
+		let view_formats = match surface_output {
+			SurfaceOutput::Sdr => vec![surface_format.add_srgb_suffix()],
+			SurfaceOutput::HdrLinear => vec![],
+		};
+
+		let surface_config = wgpu::SurfaceConfiguration {
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+			format: surface_format,
+			width,
+			height,
+			present_mode: Self::find_present_mode(&surface_caps, config.present_mode),
+			desired_maximum_frame_latency: config.max_frame_latency,
+			alpha_mode,
+			view_formats,
+		};
+
+		Ok((surface_config, surface_output))
+	}
+
+	// wider formats already quantize finer than the dither noise would hide
+	fn dither_surface(config: &RendererConfig, format: wgpu::TextureFormat) -> bool {
+		config.dither.unwrap_or(matches!(format.remove_srgb_suffix(), wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Bgra8Unorm))
+	}
+
+	fn check_max_frame_latency(max_frame_latency: u32) -> anyhow::Result<()> {
+		if max_frame_latency < 1 {
+			bail!("Maximum frame latency must be at least 1 (got {})", max_frame_latency);
+		}
+		Ok(())
+	}
+
+	fn target_format(surface_config: &wgpu::SurfaceConfiguration) -> wgpu::TextureFormat {
+		surface_config.view_formats.first().copied().unwrap_or(surface_config.format)
+	}
+
+	fn find_surface_format(surface_caps: &wgpu::SurfaceCapabilities, hdr: bool) -> anyhow::Result<(wgpu::TextureFormat, SurfaceOutput)> {
+		if hdr {
+			if surface_caps.formats.contains(&wgpu::TextureFormat::Rgba16Float) {
+				return Ok((wgpu::TextureFormat::Rgba16Float, SurfaceOutput::HdrLinear));
+			}
+			log::warn!("HDR output requested but the surface has no scRGB format, falling back to SDR");
+		}
+		// SDR output relies on an sRGB view for the transfer function, formats without one come last
+		let sdr_format = surface_caps.formats.iter().copied().find(|format| format.add_srgb_suffix().is_srgb()).or(surface_caps.formats.first().copied());
+		sdr_format.map(|format| (format, SurfaceOutput::Sdr)).ok_or(anyhow!("No supported surface formats found (surface is incompatible with adapter)"))
+	}
+
+	fn find_present_mode(surface_caps: &wgpu::SurfaceCapabilities, present_mode: wgpu::PresentMode) -> wgpu::PresentMode {
+		match present_mode {
+			wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync => present_mode,
+			_ if surface_caps.present_modes.contains(&present_mode) => present_mode,
+			_ => {
+				log::warn!("Present mode {:?} is not supported, falling back to AutoNoVsync", present_mode);
+				wgpu::PresentMode::AutoNoVsync
+			},
+		}
 	}
 
 	fn find_alpha_mode(surface_caps: &wgpu::SurfaceCapabilities) -> anyhow::Result<wgpu::CompositeAlphaMode> {
@@ -222,10 +1529,426 @@ impl Renderer {
 		surface_caps.alpha_modes.iter().min_by_key(|mode| alpha_mode_preference(**mode)).copied().ok_or(anyhow!("No supported alpha modes found (normaly should not happen)"))
 	}
 
-	fn create_render_pipeline(device: &wgpu::Device, render_pipeline_layout: wgpu::PipelineLayout, surface_config: &wgpu::SurfaceConfiguration) -> wgpu::RenderPipeline {
+	fn create_scene_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+		device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Scene Bind Group Layout"),
+			entries: &[
+				Self::storage_layout_entry(0),
+				wgpu::BindGroupLayoutEntry {
+					binding: 1,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Float { filterable: true },
+						view_dimension: wgpu::TextureViewDimension::D2,
+						multisampled: false,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 2,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+					count: None,
+				},
+				Self::storage_layout_entry(3),
+				Self::storage_layout_entry(4),
+				Self::storage_layout_entry(5),
+			],
+		})
+	}
+
+	fn storage_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+		wgpu::BindGroupLayoutEntry {
+			binding,
+			visibility: wgpu::ShaderStages::FRAGMENT,
+			ty: wgpu::BindingType::Buffer {
+				ty: wgpu::BufferBindingType::Storage { read_only: true },
+				has_dynamic_offset: false,
+				min_binding_size: None,
+			},
+			count: None,
+		}
+	}
+
+	fn create_default_material_texture(device: &wgpu::Device, queue: &wgpu::Queue) -> Texture {
+		// a checkerboard makes the UV mapping visible without any asset
+		let image = texture::checkerboard(256, [200, 200, 200, 255], [60, 60, 60, 255]);
+		Texture::from_image(device, queue, "Default Material Texture", &image)
+	}
+
+	fn clamp_anisotropy(adapter: &wgpu::Adapter, level: u16) -> u16 {
+		let supported = adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::ANISOTROPIC_FILTERING);
+		let max_level = if supported { Self::MAX_ANISOTROPY } else { 1 };
+		let clamped = level.clamp(1, max_level);
+		if clamped != level {
+			log::warn!("Anisotropy level {} is not supported, using {}", level, clamped);
+		}
+		clamped
+	}
+
+	fn create_material_sampler(device: &wgpu::Device, filter: wgpu::FilterMode, anisotropy: u16) -> wgpu::Sampler {
+		let mipmap_filter = match filter {
+			wgpu::FilterMode::Nearest => wgpu::MipmapFilterMode::Nearest,
+			wgpu::FilterMode::Linear => wgpu::MipmapFilterMode::Linear,
+		};
+		// anisotropic filtering is only valid with linear filtering
+		let anisotropy = if filter == wgpu::FilterMode::Linear { anisotropy } else { 1 };
+		device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("Material Sampler"),
+			address_mode_u: wgpu::AddressMode::Repeat,
+			address_mode_v: wgpu::AddressMode::Repeat,
+			address_mode_w: wgpu::AddressMode::Repeat,
+			mag_filter: filter,
+			min_filter: filter,
+			mipmap_filter,
+			anisotropy_clamp: anisotropy,
+			..Default::default()
+		})
+	}
+
+	fn create_scene_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, scene_buffers: &SceneBuffers, material_texture: &Texture, material_sampler: &wgpu::Sampler) -> wgpu::BindGroup {
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Scene Bind Group"),
+			layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: scene_buffers.point_lights.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: wgpu::BindingResource::TextureView(&material_texture.view),
+				},
+				wgpu::BindGroupEntry {
+					binding: 2,
+					resource: wgpu::BindingResource::Sampler(material_sampler),
+				},
+				wgpu::BindGroupEntry {
+					binding: 3,
+					resource: scene_buffers.materials.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 4,
+					resource: scene_buffers.spheres.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 5,
+					resource: scene_buffers.planes.as_entire_binding(),
+				},
+			],
+		})
+	}
+
+	fn create_camera_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+		device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Camera Bind Group Layout"),
+			entries: &[
+				Self::uniform_layout_entry(0),
+				Self::uniform_layout_entry(1),
+			],
+		})
+	}
+
+	fn uniform_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+		wgpu::BindGroupLayoutEntry {
+			binding,
+			visibility: wgpu::ShaderStages::FRAGMENT,
+			ty: wgpu::BindingType::Buffer {
+				ty: wgpu::BufferBindingType::Uniform,
+				has_dynamic_offset: false,
+				min_binding_size: None,
+			},
+			count: None,
+		}
+	}
+
+	fn create_camera_buffer(device: &wgpu::Device) -> wgpu::Buffer {
+		device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Camera Buffer"),
+			size: size_of::<CameraUniform>() as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		})
+	}
+
+	fn create_globals_buffer(device: &wgpu::Device) -> wgpu::Buffer {
+		device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Globals Buffer"),
+			size: size_of::<Globals>() as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		})
+	}
+
+	fn create_camera_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, camera_buffer: &wgpu::Buffer, globals_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Camera Bind Group"),
+			layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: camera_buffer.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: globals_buffer.as_entire_binding(),
+				},
+			],
+		})
+	}
+
+	fn create_environment_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+		device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Environment Bind Group Layout"),
+			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Float { filterable: true },
+						view_dimension: wgpu::TextureViewDimension::Cube,
+						multisampled: false,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 1,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 2,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+			],
+		})
+	}
+
+	fn create_sky_gradient_buffer(device: &wgpu::Device) -> wgpu::Buffer {
+		device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Sky Gradient Buffer"),
+			size: size_of::<SkyGradient>() as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		})
+	}
+
+	fn create_environment_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+		device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("Environment Sampler"),
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			..Default::default()
+		})
+	}
+
+	fn create_environment_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, environment: &Texture, sampler: &wgpu::Sampler, sky_gradient_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Environment Bind Group"),
+			layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: wgpu::BindingResource::TextureView(&environment.view),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: wgpu::BindingResource::Sampler(sampler),
+				},
+				wgpu::BindGroupEntry {
+					binding: 2,
+					resource: sky_gradient_buffer.as_entire_binding(),
+				},
+			],
+		})
+	}
+
+	fn create_overlay_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+		device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Overlay Bind Group Layout"),
+			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 1,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+			],
+		})
+	}
+
+	fn create_grid_buffer(device: &wgpu::Device) -> wgpu::Buffer {
+		device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Grid Buffer"),
+			size: size_of::<GridUniform>() as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		})
+	}
+
+	fn create_clip_plane_buffer(device: &wgpu::Device) -> wgpu::Buffer {
+		device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Clip Plane Buffer"),
+			size: size_of::<[[f32; 4]; Self::MAX_CLIP_PLANES]>() as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		})
+	}
+
+	fn create_overlay_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, grid_buffer: &wgpu::Buffer, clip_plane_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Overlay Bind Group"),
+			layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: grid_buffer.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: clip_plane_buffer.as_entire_binding(),
+				},
+			],
+		})
+	}
+
+	fn create_fxaa_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+		device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("FXAA Bind Group Layout"),
+			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Float { filterable: true },
+						view_dimension: wgpu::TextureViewDimension::D2,
+						multisampled: false,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 1,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+					count: None,
+				},
+			],
+		})
+	}
+
+	fn create_fxaa_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+		// the blur taps land between texels, linear filtering does part of the averaging
+		device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("FXAA Sampler"),
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			..Default::default()
+		})
+	}
+
+	// same format as the surface view so the main pipeline can render into it unchanged
+	fn create_fxaa_target(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, sampler: &wgpu::Sampler, surface_config: &wgpu::SurfaceConfiguration) -> FxaaTarget {
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("FXAA Target"),
+			size: wgpu::Extent3d {
+				width: surface_config.width,
+				height: surface_config.height,
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: Self::target_format(surface_config),
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+			view_formats: &[],
+		});
+		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("FXAA Bind Group"),
+			layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: wgpu::BindingResource::TextureView(&view),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: wgpu::BindingResource::Sampler(sampler),
+				},
+			],
+		});
+		FxaaTarget { view, bind_group }
+	}
+
+	// follows the surface size and format, dropped while FXAA is disabled
+	fn rebuild_fxaa_target(&mut self) {
+		self.fxaa_target = self.config.fxaa.then(|| Self::create_fxaa_target(&self.device, &self.fxaa_bind_group_layout, &self.fxaa_sampler, &self.surface_config));
+	}
+
+	fn rebuild_scene_bind_group(&mut self) {
+		self.scene_bind_group = Self::create_scene_bind_group(&self.device, &self.scene_bind_group_layout, &self.scene_buffers, &self.material_texture, &self.material_sampler);
+	}
+
+	fn check_scene_upload(&self, scene: &Scene) -> anyhow::Result<()> {
+		let point_lights: Vec<PointLight> = self.read_buffer(&self.scene_buffers.point_lights)?;
+		if !point_lights.starts_with(scene.point_lights()) {
+			bail!("Point light buffer does not match the scene lights");
+		}
+		let materials: Vec<Material> = self.read_buffer(&self.scene_buffers.materials)?;
+		if !materials.starts_with(scene.materials()) {
+			bail!("Material buffer does not match the scene materials");
+		}
+		let spheres: Vec<Sphere> = self.read_buffer(&self.scene_buffers.spheres)?;
+		if !spheres.starts_with(scene.spheres()) {
+			bail!("Sphere buffer does not match the scene spheres");
+		}
+		let planes: Vec<Plane> = self.read_buffer(&self.scene_buffers.planes)?;
+		if !planes.starts_with(scene.planes()) {
+			bail!("Plane buffer does not match the scene planes");
+		}
+		Ok(())
+	}
+
+	fn color_target(format: wgpu::TextureFormat) -> Option<wgpu::ColorTargetState> {
+		Some(wgpu::ColorTargetState {
+			format,
+			// no blending, float32 capture targets are not blendable without an extra feature
+			blend: None,
+			write_mask: wgpu::ColorWrites::ALL,
+		})
+	}
+
+	fn create_render_pipeline(device: &wgpu::Device, render_pipeline_layout: &wgpu::PipelineLayout, fragment_entry_point: &str, targets: &[Option<wgpu::ColorTargetState>]) -> wgpu::RenderPipeline {
 		device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Main Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
+            layout: Some(render_pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &load_shader!(device, "vertex_shader", "Vertex Shader"),
                 entry_point: Some("vs_main"),
@@ -234,15 +1957,8 @@ impl Renderer {
             },
             fragment: Some(wgpu::FragmentState {
                 module: &load_shader!(device, "fragment_shader", "Fragment Shader"),
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
+                entry_point: Some(fragment_entry_point),
+                targets,
                 compilation_options: Default::default(),
             }),
             primitive: wgpu::PrimitiveState {
@@ -265,4 +1981,64 @@ impl Renderer {
         })
 	}
 
-}
\ No newline at end of file
+	fn create_fxaa_pipeline(device: &wgpu::Device, fxaa_pipeline_layout: &wgpu::PipelineLayout, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+		device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+			label: Some("FXAA Pipeline"),
+			layout: Some(fxaa_pipeline_layout),
+			vertex: wgpu::VertexState {
+				module: &load_shader!(device, "vertex_shader", "Vertex Shader"),
+				entry_point: Some("vs_main"),
+				buffers: &[],
+				compilation_options: Default::default(),
+			},
+			fragment: Some(wgpu::FragmentState {
+				module: &load_shader!(device, "fxaa_shader", "FXAA Shader"),
+				entry_point: Some("fs_main"),
+				targets: &[Some(wgpu::ColorTargetState {
+					format,
+					blend: None,
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+				compilation_options: Default::default(),
+			}),
+			primitive: wgpu::PrimitiveState::default(),
+			depth_stencil: None,
+			multisample: wgpu::MultisampleState::default(),
+			multiview_mask: None,
+			cache: None,
+		})
+	}
+
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn zero_lights_keep_a_dummy_light_slot() {
+		let mut scene = Scene::new(0);
+		let material = scene.add_material(Material::new([1.0, 1.0, 1.0], false));
+		scene.add_plane(Plane::new([0.0, 1.0, 0.0], 0.0, material)).unwrap();
+
+		let size = SceneBuffers::storage_buffer_size::<PointLight>(scene.point_lights().len(), scene.max_point_lights());
+		assert_eq!(size, size_of::<PointLight>() as wgpu::BufferAddress);
+
+		// the shader loops over min(point_light_count, arrayLength), so the dummy slot is never read
+		let mut immediate = Immediate::new(64, 64);
+		immediate.update_scene_counts(&scene);
+		assert_eq!(immediate.point_light_count, 0);
+		assert_eq!(immediate.plane_count, 1);
+	}
+
+	#[test]
+	fn light_buffer_is_sized_to_capacity() {
+		let mut scene = Scene::new(8);
+		scene.add_point_light(PointLight::new([0.0, 1.0, 0.0], [1.0, 1.0, 1.0], 5.0)).unwrap();
+		let size = SceneBuffers::storage_buffer_size::<PointLight>(scene.point_lights().len(), scene.max_point_lights());
+		assert_eq!(size, 8 * size_of::<PointLight>() as wgpu::BufferAddress);
+
+		let mut immediate = Immediate::new(64, 64);
+		immediate.update_scene_counts(&scene);
+		assert_eq!(immediate.point_light_count, 1);
+	}
+}
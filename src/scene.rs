@@ -0,0 +1,537 @@
+use crate::camera::{Camera, Projection};
+use crate::color::Color;
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use glam::Vec3;
+use serde::Deserialize;
+
+use anyhow::{Context, anyhow, bail};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointLight {
+	pub position: [f32; 3],
+	pub radius: f32,
+	pub color: [f32; 3],
+	_padding: f32,
+}
+
+impl PointLight {
+	pub fn new(position: [f32; 3], color: [f32; 3], radius: f32) -> Self {
+		Self {
+			position,
+			radius,
+			color,
+			_padding: 0.0,
+		}
+	}
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Material {
+	pub albedo: [f32; 3],
+	textured: u32,
+	// added to the shaded color, independent of lighting
+	pub emissive: [f32; 3],
+	_padding: f32,
+}
+
+impl Material {
+	pub fn new(albedo: [f32; 3], textured: bool) -> Self {
+		Self {
+			albedo,
+			textured: textured.into(),
+			emissive: [0.0; 3],
+			_padding: 0.0,
+		}
+	}
+
+	pub fn with_emissive(mut self, emissive: [f32; 3]) -> Self {
+		self.emissive = emissive;
+		self
+	}
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Sphere {
+	pub center: [f32; 3],
+	pub radius: f32,
+	pub material: u32,
+	_padding: [u32; 3],
+}
+
+impl Sphere {
+	pub fn new(center: [f32; 3], radius: f32, material: u32) -> Self {
+		Self {
+			center,
+			radius,
+			material,
+			_padding: [0; 3],
+		}
+	}
+
+	// nearest distance along a normalized direction, mirrors intersect_sphere in the fragment shader
+	pub fn intersect(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+		let oc = origin - Vec3::from(self.center);
+		let b = oc.dot(direction);
+		let c = oc.length_squared() - self.radius * self.radius;
+		let discriminant = b * b - c;
+		if discriminant < 0.0 {
+			return None;
+		}
+		let root = discriminant.sqrt();
+		[-b - root, -b + root].into_iter().find(|&t| RAY_EPSILON < t)
+	}
+}
+
+// points p with dot(normal, p) == offset
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Plane {
+	pub normal: [f32; 3],
+	pub offset: f32,
+	pub material: u32,
+	_padding: [u32; 3],
+}
+
+impl Plane {
+	pub fn new(normal: [f32; 3], offset: f32, material: u32) -> Self {
+		Self {
+			normal: Vec3::from(normal).normalize_or(Vec3::Y).to_array(),
+			offset,
+			material,
+			_padding: [0; 3],
+		}
+	}
+
+	pub fn intersect(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+		let normal = Vec3::from(self.normal);
+		let denom = normal.dot(direction);
+		if denom == 0.0 {
+			return None;
+		}
+		let t = (self.offset - normal.dot(origin)) / denom;
+		(RAY_EPSILON < t).then_some(t)
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Primitive {
+	Sphere(usize),
+	Plane(usize),
+}
+
+impl Primitive {
+	// decodes the ids of the object id render target, 0 is a miss and planes follow the spheres
+	pub fn from_object_id(object_id: u32, sphere_count: usize) -> Option<Self> {
+		let index = (object_id as usize).checked_sub(1)?;
+		if index < sphere_count {
+			Some(Primitive::Sphere(index))
+		} else {
+			Some(Primitive::Plane(index - sphere_count))
+		}
+	}
+
+	// inverse of from_object_id
+	pub fn object_id(self, sphere_count: usize) -> u32 {
+		let index = match self {
+			Primitive::Sphere(index) => index,
+			Primitive::Plane(index) => sphere_count + index,
+		};
+		index as u32 + 1
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SceneHit {
+	pub primitive: Primitive,
+	pub distance: f32,
+	pub position: Vec3,
+}
+
+#[derive(Clone, Debug)]
+pub struct SceneCamera {
+	pub name: String,
+	pub camera: Camera,
+}
+
+// self intersection tolerance, same as the fragment shader
+const RAY_EPSILON: f32 = 1e-4;
+
+#[derive(Clone)]
+pub struct Scene {
+	point_lights: Vec<PointLight>,
+	max_point_lights: usize,
+	materials: Vec<Material>,
+	spheres: Vec<Sphere>,
+	planes: Vec<Plane>,
+	cameras: Vec<SceneCamera>,
+}
+
+impl Default for Scene {
+	fn default() -> Self {
+		Self::new(Self::DEFAULT_MAX_POINT_LIGHTS)
+	}
+}
+
+impl Scene {
+	pub const DEFAULT_MAX_POINT_LIGHTS: usize = 64;
+
+	//public
+
+	pub fn new(max_point_lights: usize) -> Self {
+		Self {
+			point_lights: Vec::new(),
+			max_point_lights,
+			materials: Vec::new(),
+			spheres: Vec::new(),
+			planes: Vec::new(),
+			cameras: Vec::new(),
+		}
+	}
+
+	pub fn add_point_light(&mut self, light: PointLight) -> anyhow::Result<()> {
+		if self.max_point_lights <= self.point_lights.len() {
+			bail!("Point light capacity exceeded (max {} lights)", self.max_point_lights);
+		}
+		self.point_lights.push(light);
+		Ok(())
+	}
+
+	pub fn add_material(&mut self, material: Material) -> u32 {
+		self.materials.push(material);
+		(self.materials.len() - 1) as u32
+	}
+
+	pub fn add_sphere(&mut self, sphere: Sphere) -> anyhow::Result<()> {
+		self.check_material(sphere.material)?;
+		self.spheres.push(sphere);
+		Ok(())
+	}
+
+	pub fn add_plane(&mut self, plane: Plane) -> anyhow::Result<()> {
+		self.check_material(plane.material)?;
+		self.planes.push(plane);
+		Ok(())
+	}
+
+	pub fn add_camera(&mut self, name: impl Into<String>, camera: Camera) {
+		self.cameras.push(SceneCamera {
+			name: name.into(),
+			camera,
+		});
+	}
+
+	pub fn point_lights(&self) -> &[PointLight] {
+		&self.point_lights
+	}
+
+	pub fn max_point_lights(&self) -> usize {
+		self.max_point_lights
+	}
+
+	pub fn materials(&self) -> &[Material] {
+		&self.materials
+	}
+
+	pub fn spheres(&self) -> &[Sphere] {
+		&self.spheres
+	}
+
+	pub fn planes(&self) -> &[Plane] {
+		&self.planes
+	}
+
+	// the camera a scene starts with
+	pub fn camera(&self) -> Option<&Camera> {
+		self.cameras.first().map(|scene_camera| &scene_camera.camera)
+	}
+
+	pub fn cameras(&self) -> &[SceneCamera] {
+		&self.cameras
+	}
+
+	// linear scan over all primitives, clip planes are not taken into account. With `solo` only
+	// that primitive is tested, matching what the renderer draws
+	pub fn intersect_solo(&self, origin: Vec3, direction: Vec3, solo: Option<Primitive>) -> Option<SceneHit> {
+		let visible = |primitive: Primitive| solo.is_none_or(|solo| solo == primitive);
+		let spheres = self.spheres.iter().enumerate().filter(|(index, _)| visible(Primitive::Sphere(*index))).filter_map(|(index, sphere)| Some((Primitive::Sphere(index), sphere.intersect(origin, direction)?)));
+		let planes = self.planes.iter().enumerate().filter(|(index, _)| visible(Primitive::Plane(*index))).filter_map(|(index, plane)| Some((Primitive::Plane(index), plane.intersect(origin, direction)?)));
+		spheres.chain(planes).min_by(|(_, a), (_, b)| a.total_cmp(b)).map(|(primitive, distance)| SceneHit {
+			primitive,
+			distance,
+			position: origin + distance * direction,
+		})
+	}
+
+	//private
+
+	fn check_material(&self, material: u32) -> anyhow::Result<()> {
+		if self.materials.len() <= material as usize {
+			bail!("Material index {} out of range ({} materials)", material, self.materials.len());
+		}
+		Ok(())
+	}
+}
+
+// scene files found in one directory, each is only loaded once it is selected
+pub struct SceneManager {
+	paths: Vec<PathBuf>,
+	current: Option<usize>,
+}
+
+impl SceneManager {
+	pub const SUPPORTED_EXTENSIONS: [&str; 1] = ["ron"];
+
+	//public
+
+	pub fn from_dir(path: &Path) -> anyhow::Result<Self> {
+		let entries = std::fs::read_dir(path).with_context(|| format!("Failed to read scene directory {}", path.display()))?;
+		let mut paths = Vec::new();
+		for entry in entries {
+			let entry_path = entry.with_context(|| format!("Failed to read scene directory {}", path.display()))?.path();
+			let supported = entry_path.extension().and_then(|extension| extension.to_str()).is_some_and(|extension| Self::SUPPORTED_EXTENSIONS.iter().any(|supported| supported.eq_ignore_ascii_case(extension)));
+			if supported && entry_path.is_file() {
+				paths.push(entry_path);
+			}
+		}
+		if paths.is_empty() {
+			bail!("Scene directory {} has no {} files", path.display(), Self::SUPPORTED_EXTENSIONS.join(", "));
+		}
+		paths.sort();
+		Ok(Self { paths, current: None })
+	}
+
+	pub fn current_path(&self) -> Option<&Path> {
+		self.current.map(|index| self.paths[index].as_path())
+	}
+
+	pub fn current_name(&self) -> Option<&str> {
+		self.current_path()?.file_name()?.to_str()
+	}
+
+	// files that fail to load are logged and skipped, None once every file failed
+	pub fn next(&mut self) -> Option<Scene> {
+		let start = self.current.unwrap_or(self.paths.len() - 1);
+		self.select_from(start, 1)
+	}
+
+	pub fn previous(&mut self) -> Option<Scene> {
+		let start = self.current.unwrap_or(0);
+		self.select_from(start, self.paths.len() - 1)
+	}
+
+	//private
+
+	fn select_from(&mut self, start: usize, step: usize) -> Option<Scene> {
+		let mut index = start;
+		for _ in 0..self.paths.len() {
+			index = (index + step) % self.paths.len();
+			match load_ron(&self.paths[index]) {
+				Ok(scene) => {
+					self.current = Some(index);
+					return Some(scene);
+				},
+				Err(e) => log::warn!("Skipping scene: {:?}", e),
+			}
+		}
+		None
+	}
+}
+
+#[derive(Deserialize)]
+struct SceneDescription {
+	// kept for scenes with a single viewpoint, it goes before the ones in `cameras`
+	#[serde(default)]
+	camera: Option<CameraDescription>,
+	#[serde(default)]
+	cameras: Vec<CameraDescription>,
+	#[serde(default = "SceneDescription::default_max_point_lights")]
+	max_point_lights: usize,
+	#[serde(default)]
+	materials: BTreeMap<String, MaterialDescription>,
+	#[serde(default)]
+	spheres: Vec<SphereDescription>,
+	#[serde(default)]
+	planes: Vec<PlaneDescription>,
+	#[serde(default)]
+	meshes: Vec<MeshDescription>,
+	#[serde(default)]
+	point_lights: Vec<PointLightDescription>,
+}
+
+impl SceneDescription {
+	fn default_max_point_lights() -> usize {
+		Scene::DEFAULT_MAX_POINT_LIGHTS
+	}
+}
+
+#[derive(Deserialize)]
+struct CameraDescription {
+	#[serde(default)]
+	name: Option<String>,
+	position: [f32; 3],
+	target: [f32; 3],
+	fov_y_degrees: f32,
+}
+
+#[derive(Deserialize)]
+struct MaterialDescription {
+	albedo: Color,
+	#[serde(default)]
+	textured: bool,
+	#[serde(default)]
+	emissive: Color,
+}
+
+#[derive(Deserialize)]
+struct SphereDescription {
+	center: [f32; 3],
+	radius: f32,
+	material: String,
+}
+
+#[derive(Deserialize)]
+struct PlaneDescription {
+	normal: [f32; 3],
+	offset: f32,
+	material: String,
+}
+
+#[derive(Deserialize)]
+struct MeshDescription {
+	path: String,
+	material: String,
+}
+
+#[derive(Deserialize)]
+struct PointLightDescription {
+	position: [f32; 3],
+	color: Color,
+	radius: f32,
+}
+
+pub fn load_ron(path: &Path) -> anyhow::Result<Scene> {
+	let contents = std::fs::read_to_string(path).with_context(|| format!("Failed to read scene file {}", path.display()))?;
+	let description: SceneDescription = ron::from_str(&contents).with_context(|| format!("Failed to parse scene file {}", path.display()))?;
+	build_scene(description, path.parent().unwrap_or(Path::new(""))).with_context(|| format!("Invalid scene file {}", path.display()))
+}
+
+fn build_scene(description: SceneDescription, base_dir: &Path) -> anyhow::Result<Scene> {
+	let mut scene = Scene::new(description.max_point_lights);
+
+	let mut material_indices = HashMap::new();
+	for (name, material) in &description.materials {
+		let index = scene.add_material(Material::new(material.albedo.into(), material.textured).with_emissive(material.emissive.into()));
+		material_indices.insert(name.as_str(), index);
+	}
+	let material_index = |name: &str| material_indices.get(name).copied().ok_or_else(|| anyhow!("Unknown material \"{}\"", name));
+
+	for sphere in &description.spheres {
+		scene.add_sphere(Sphere::new(sphere.center, sphere.radius, material_index(&sphere.material)?))?;
+	}
+	for plane in &description.planes {
+		scene.add_plane(Plane::new(plane.normal, plane.offset, material_index(&plane.material)?))?;
+	}
+	for mesh in &description.meshes {
+		material_index(&mesh.material)?;
+		let mesh_path = base_dir.join(&mesh.path);
+		if !mesh_path.is_file() {
+			bail!("Mesh file {} does not exist", mesh_path.display());
+		}
+		log::warn!("Mesh primitives are not rendered yet, skipping {}", mesh_path.display());
+	}
+	for light in &description.point_lights {
+		scene.add_point_light(PointLight::new(light.position, light.color.into(), light.radius))?;
+	}
+
+	for (index, camera_description) in description.camera.iter().chain(&description.cameras).enumerate() {
+		let mut camera = Camera::default();
+		camera.look_at(Vec3::from(camera_description.position), Vec3::from(camera_description.target));
+		camera.projection = Projection::Perspective { fov_y: camera_description.fov_y_degrees.to_radians() };
+		let name = camera_description.name.clone().unwrap_or_else(|| format!("Camera {}", index + 1));
+		scene.add_camera(name, camera);
+	}
+
+	Ok(scene)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_near(actual: Option<f32>, expected: f32) {
+		let actual = actual.expect("expected a hit");
+		assert!((actual - expected).abs() < 1e-5, "expected {} but got {}", expected, actual);
+	}
+
+	#[test]
+	fn sphere_hit_returns_near_root() {
+		let sphere = Sphere::new([0.0, 0.0, -5.0], 1.0, 0);
+		assert_near(sphere.intersect(Vec3::ZERO, Vec3::NEG_Z), 4.0);
+	}
+
+	#[test]
+	fn sphere_miss() {
+		let sphere = Sphere::new([0.0, 0.0, -5.0], 1.0, 0);
+		assert_eq!(sphere.intersect(Vec3::ZERO, Vec3::Z), None);
+		assert_eq!(sphere.intersect(Vec3::new(0.0, 2.0, 0.0), Vec3::NEG_Z), None);
+	}
+
+	#[test]
+	fn sphere_hit_from_inside_returns_far_root() {
+		let sphere = Sphere::new([0.0, 0.0, 0.0], 2.0, 0);
+		assert_near(sphere.intersect(Vec3::ZERO, Vec3::X), 2.0);
+	}
+
+	#[test]
+	fn plane_hit_and_parallel_miss() {
+		let plane = Plane::new([0.0, 1.0, 0.0], 0.0, 0);
+		assert_near(plane.intersect(Vec3::new(0.0, 3.0, 0.0), Vec3::NEG_Y), 3.0);
+		assert_eq!(plane.intersect(Vec3::new(0.0, 3.0, 0.0), Vec3::X), None);
+		assert_eq!(plane.intersect(Vec3::new(0.0, 3.0, 0.0), Vec3::Y), None);
+	}
+
+	#[test]
+	fn lights_over_plane_fixture_loads() {
+		let scene = load_ron(&Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/lights_over_plane.ron")).unwrap();
+		assert_eq!(scene.point_lights().len(), 4);
+		assert_eq!(scene.planes().len(), 1);
+		assert_eq!(scene.max_point_lights(), 8);
+		assert!(scene.camera().is_some());
+	}
+
+	#[test]
+	fn scene_without_lights_has_none() {
+		let description: SceneDescription = ron::from_str("(materials: {\"white\": (albedo: [1.0, 1.0, 1.0])}, planes: [(normal: (0.0, 1.0, 0.0), offset: 0.0, material: \"white\")])").unwrap();
+		let scene = build_scene(description, Path::new("")).unwrap();
+		assert!(scene.point_lights().is_empty());
+		assert_eq!(scene.planes().len(), 1);
+	}
+
+	#[test]
+	fn scene_picks_nearest_primitive() {
+		let mut scene = Scene::new(0);
+		let material = scene.add_material(Material::new([1.0, 1.0, 1.0], false));
+		scene.add_sphere(Sphere::new([0.0, 0.0, -10.0], 1.0, material)).unwrap();
+		scene.add_sphere(Sphere::new([0.0, 0.0, -5.0], 1.0, material)).unwrap();
+		scene.add_plane(Plane::new([0.0, 0.0, 1.0], -20.0, material)).unwrap();
+
+		let hit = scene.intersect_solo(Vec3::ZERO, Vec3::NEG_Z, None).expect("expected a hit");
+		assert_eq!(hit.primitive, Primitive::Sphere(1));
+		assert!((hit.distance - 4.0).abs() < 1e-5);
+		assert!((hit.position - Vec3::new(0.0, 0.0, -4.0)).length() < 1e-5);
+
+		let hit = scene.intersect_solo(Vec3::new(0.0, 5.0, 0.0), Vec3::NEG_Z, None).expect("expected a hit");
+		assert_eq!(hit.primitive, Primitive::Plane(0));
+		assert!(scene.intersect_solo(Vec3::ZERO, Vec3::Z, None).is_none());
+
+		// a soloed primitive is hit through the hidden ones in front of it
+		let hit = scene.intersect_solo(Vec3::ZERO, Vec3::NEG_Z, Some(Primitive::Sphere(0))).expect("expected a hit");
+		assert_eq!(hit.primitive, Primitive::Sphere(0));
+		assert!((hit.distance - 9.0).abs() < 1e-5);
+	}
+}
@@ -0,0 +1,263 @@
+use wesl::include_wesl;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::NoUninit)]
+struct GlyphInstance {
+	position: [f32; 2],
+	scale: f32,
+	glyph: u32,
+	color: [f32; 4],
+}
+
+// 3x5 pixel glyphs for ASCII 32..=126, row major from the top left, most significant bit first
+const FONT: [u16; 95] = [
+	0x0000, 0x2482, 0x5A00, 0x5F7D, 0x3C9E, 0x52A5, 0x2AAB, 0x2400,
+	0x1491, 0x4494, 0x0AA8, 0x05D0, 0x0014, 0x01C0, 0x0002, 0x12A4,
+	0x7B6F, 0x2C97, 0x73E7, 0x73CF, 0x5BC9, 0x79CF, 0x79EF, 0x7249,
+	0x7BEF, 0x7BCF, 0x0410, 0x0414, 0x1511, 0x0E38, 0x4454, 0x72C2,
+	0x7BE7, 0x2BED, 0x6BAE, 0x3923, 0x6B6E, 0x79E7, 0x79E4, 0x396B,
+	0x5BED, 0x7497, 0x126A, 0x5BAD, 0x4927, 0x5FED, 0x6B6D, 0x2B6A,
+	0x6BA4, 0x2B7B, 0x6BAD, 0x388E, 0x7492, 0x5B6F, 0x5B6A, 0x5BFD,
+	0x5AAD, 0x5A92, 0x72A7, 0x6926, 0x4889, 0x324B, 0x2A00, 0x0007,
+	0x4400, 0x2BED, 0x6BAE, 0x3923, 0x6B6E, 0x79E7, 0x79E4, 0x396B,
+	0x5BED, 0x7497, 0x126A, 0x5BAD, 0x4927, 0x5FED, 0x6B6D, 0x2B6A,
+	0x6BA4, 0x2B7B, 0x6BAD, 0x388E, 0x7492, 0x5B6F, 0x5B6A, 0x5BFD,
+	0x5AAD, 0x5A92, 0x72A7, 0x3593, 0x2492, 0x64D6, 0x0CC0,
+];
+
+pub struct TextRenderer {
+	pipeline: wgpu::RenderPipeline,
+	pipeline_layout: wgpu::PipelineLayout,
+	bind_group: wgpu::BindGroup,
+	instance_buffer: wgpu::Buffer,
+	instance_count: u32,
+	queued: Vec<GlyphInstance>,
+}
+
+impl TextRenderer {
+	const FIRST_GLYPH: u8 = b' ';
+	const GLYPH_WIDTH: u32 = 3;
+	const GLYPH_HEIGHT: u32 = 5;
+	// one empty pixel between glyphs so nearest sampling never bleeds into a neighbour
+	const CELL_WIDTH: u32 = Self::GLYPH_WIDTH + 1;
+	pub const LINE_HEIGHT: u32 = Self::GLYPH_HEIGHT + 2;
+	const INITIAL_CAPACITY: usize = 256;
+
+	//public
+
+	pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, target_format: wgpu::TextureFormat) -> Self {
+		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Text Bind Group Layout"),
+			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Float { filterable: true },
+						view_dimension: wgpu::TextureViewDimension::D2,
+						multisampled: false,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 1,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+					count: None,
+				},
+			],
+		});
+
+		let atlas = Self::create_atlas(device, queue);
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("Font Sampler"),
+			mag_filter: wgpu::FilterMode::Nearest,
+			min_filter: wgpu::FilterMode::Nearest,
+			..Default::default()
+		});
+		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Text Bind Group"),
+			layout: &bind_group_layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: wgpu::BindingResource::TextureView(&atlas),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: wgpu::BindingResource::Sampler(&sampler),
+				},
+			],
+		});
+
+		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("Text Pipeline Layout"),
+			bind_group_layouts: &[&bind_group_layout],
+			immediate_size: size_of::<[f32; 2]>() as u32,
+		});
+		let pipeline = Self::create_pipeline(device, &pipeline_layout, target_format);
+
+		Self {
+			pipeline,
+			pipeline_layout,
+			bind_group,
+			instance_buffer: Self::create_instance_buffer(device, Self::INITIAL_CAPACITY),
+			instance_count: 0,
+			queued: Vec::new(),
+		}
+	}
+
+	pub fn set_target_format(&mut self, device: &wgpu::Device, target_format: wgpu::TextureFormat) {
+		self.pipeline = Self::create_pipeline(device, &self.pipeline_layout, target_format);
+	}
+
+	// x and y are the top left corner in pixels, scale is the size of one font pixel.
+	// Lowercase letters are drawn as uppercase, other characters outside ASCII as '?'.
+	pub fn queue_text(&mut self, text: &str, x: f32, y: f32, scale: f32, color: [f32; 4]) {
+		let (mut pen_x, mut pen_y) = (x, y);
+		for character in text.chars() {
+			match character {
+				'\n' => {
+					pen_x = x;
+					pen_y += (Self::LINE_HEIGHT as f32) * scale;
+					continue;
+				}
+				' ' => (),
+				_ => self.queued.push(GlyphInstance {
+					position: [pen_x, pen_y],
+					scale,
+					glyph: Self::glyph_index(character),
+					color,
+				}),
+			}
+			pen_x += (Self::CELL_WIDTH as f32) * scale;
+		}
+	}
+
+	// drops the text queued for a frame that is not drawn
+	pub fn clear_queue(&mut self) {
+		self.queued.clear();
+	}
+
+	// uploads the text queued since the last call, the queue starts empty again
+	pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+		let capacity = (self.instance_buffer.size() as usize) / size_of::<GlyphInstance>();
+		if capacity < self.queued.len() {
+			self.instance_buffer = Self::create_instance_buffer(device, self.queued.len().next_power_of_two());
+		}
+		if !self.queued.is_empty() {
+			queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&self.queued));
+		}
+		self.instance_count = self.queued.len() as u32;
+		self.queued.clear();
+	}
+
+	// returns the number of glyphs drawn
+	pub fn draw(&self, render_pass: &mut wgpu::RenderPass, screen_size: [f32; 2]) -> u32 {
+		if self.instance_count == 0 {
+			return 0;
+		}
+		render_pass.set_pipeline(&self.pipeline);
+		render_pass.set_bind_group(0, &self.bind_group, &[]);
+		render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+		render_pass.set_immediates(0, bytemuck::bytes_of(&screen_size));
+		render_pass.draw(0..6, 0..self.instance_count);
+		self.instance_count
+	}
+
+	//private
+
+	fn glyph_index(character: char) -> u32 {
+		let character = character.to_ascii_uppercase();
+		let index = if character.is_ascii_graphic() { character as u8 } else { b'?' };
+		(index - Self::FIRST_GLYPH) as u32
+	}
+
+	fn create_atlas(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::TextureView {
+		let width = FONT.len() as u32 * Self::CELL_WIDTH;
+		let height = Self::GLYPH_HEIGHT;
+		let mut pixels = vec![0u8; (width * height) as usize];
+		for (index, bits) in FONT.iter().enumerate() {
+			for row in 0..Self::GLYPH_HEIGHT {
+				for column in 0..Self::GLYPH_WIDTH {
+					let bit = Self::GLYPH_WIDTH * Self::GLYPH_HEIGHT - 1 - (row * Self::GLYPH_WIDTH + column);
+					if (bits >> bit) & 1 == 1 {
+						pixels[(row * width + index as u32 * Self::CELL_WIDTH + column) as usize] = 255;
+					}
+				}
+			}
+		}
+
+		let size = wgpu::Extent3d {
+			width,
+			height,
+			depth_or_array_layers: 1,
+		};
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("Font Atlas"),
+			size,
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: wgpu::TextureFormat::R8Unorm,
+			usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+			view_formats: &[],
+		});
+		queue.write_texture(
+			texture.as_image_copy(),
+			&pixels,
+			wgpu::TexelCopyBufferLayout {
+				offset: 0,
+				bytes_per_row: Some(width),
+				rows_per_image: Some(height),
+			},
+			size,
+		);
+		texture.create_view(&wgpu::TextureViewDescriptor::default())
+	}
+
+	fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+		device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Glyph Instance Buffer"),
+			size: (capacity * size_of::<GlyphInstance>()) as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		})
+	}
+
+	fn create_pipeline(device: &wgpu::Device, layout: &wgpu::PipelineLayout, target_format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+		let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+			label: Some("Text Shader"),
+			source: wgpu::ShaderSource::Wgsl(include_wesl!("text_shader").into()),
+		});
+		device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+			label: Some("Text Render Pipeline"),
+			layout: Some(layout),
+			vertex: wgpu::VertexState {
+				module: &module,
+				entry_point: Some("vs_main"),
+				buffers: &[wgpu::VertexBufferLayout {
+					array_stride: size_of::<GlyphInstance>() as wgpu::BufferAddress,
+					step_mode: wgpu::VertexStepMode::Instance,
+					attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32, 2 => Uint32, 3 => Float32x4],
+				}],
+				compilation_options: Default::default(),
+			},
+			fragment: Some(wgpu::FragmentState {
+				module: &module,
+				entry_point: Some("fs_main"),
+				targets: &[Some(wgpu::ColorTargetState {
+					format: target_format,
+					blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+				compilation_options: Default::default(),
+			}),
+			primitive: wgpu::PrimitiveState::default(),
+			depth_stencil: None,
+			multisample: wgpu::MultisampleState::default(),
+			multiview_mask: None,
+			cache: None,
+		})
+	}
+}
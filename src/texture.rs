@@ -0,0 +1,139 @@
+use std::path::Path;
+use image::{Rgba, RgbaImage};
+use image::imageops::FilterType;
+
+use anyhow::{Context, bail};
+
+pub struct Texture {
+	pub texture: wgpu::Texture,
+	pub view: wgpu::TextureView,
+}
+
+pub fn checkerboard(size: u32, color_a: [u8; 4], color_b: [u8; 4]) -> RgbaImage {
+	const CHECKS: u32 = 8;
+	let check_size = (size / CHECKS).max(1);
+	RgbaImage::from_fn(size, size, |x, y| {
+		if (x / check_size + y / check_size) % 2 == 0 {
+			Rgba(color_a)
+		} else {
+			Rgba(color_b)
+		}
+	})
+}
+
+pub fn solid(color: [u8; 4]) -> RgbaImage {
+	RgbaImage::from_pixel(1, 1, Rgba(color))
+}
+
+impl Texture {
+	pub const CUBE_FACES: usize = 6;
+
+	//public
+
+	pub fn from_image(device: &wgpu::Device, queue: &wgpu::Queue, label: &str, image: &RgbaImage) -> Self {
+		let mip_level_count = image.width().max(image.height()).ilog2() + 1;
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some(label),
+			size: wgpu::Extent3d {
+				width: image.width(),
+				height: image.height(),
+				depth_or_array_layers: 1,
+			},
+			mip_level_count,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: wgpu::TextureFormat::Rgba8UnormSrgb,
+			usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+			view_formats: &[],
+		});
+
+		let mut level = image.clone();
+		for mip_level in 0..mip_level_count {
+			if 0 < mip_level {
+				level = image::imageops::resize(&level, (level.width() / 2).max(1), (level.height() / 2).max(1), FilterType::Triangle);
+			}
+			Self::write_layer(queue, &texture, mip_level, 0, &level);
+		}
+
+		let view = texture.create_view(&wgpu::TextureViewDescriptor {
+			label: Some(label),
+			..Default::default()
+		});
+
+		Self { texture, view }
+	}
+
+	pub fn cube_from_files(device: &wgpu::Device, queue: &wgpu::Queue, faces: [&Path; Self::CUBE_FACES]) -> anyhow::Result<Self> {
+		let mut images = Vec::with_capacity(Self::CUBE_FACES);
+		for path in faces {
+			let image = image::open(path).with_context(|| format!("Failed to load cubemap face {}", path.display()))?.to_rgba8();
+			if image.width() != image.height() {
+				bail!("Cubemap face {} is not square ({}x{})", path.display(), image.width(), image.height());
+			}
+			if let Some(first) = images.first() && first.dimensions() != image.dimensions() {
+				bail!("Cubemap face {} is {}x{} but the first face is {}x{}", path.display(), image.width(), image.height(), first.width(), first.height());
+			}
+			images.push(image);
+		}
+		Ok(Self::create_cube(device, queue, "Cubemap Texture", &images))
+	}
+
+	pub fn cube_solid(device: &wgpu::Device, queue: &wgpu::Queue, color: [u8; 4]) -> Self {
+		let face = solid(color);
+		Self::create_cube(device, queue, "Solid Cubemap Texture", &vec![face; Self::CUBE_FACES])
+	}
+
+	//private
+
+	fn create_cube(device: &wgpu::Device, queue: &wgpu::Queue, label: &str, faces: &[RgbaImage]) -> Self {
+		let size = faces[0].width();
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some(label),
+			size: wgpu::Extent3d {
+				width: size,
+				height: size,
+				depth_or_array_layers: Self::CUBE_FACES as u32,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: wgpu::TextureFormat::Rgba8UnormSrgb,
+			usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+			view_formats: &[],
+		});
+
+		for (layer, face) in faces.iter().enumerate() {
+			Self::write_layer(queue, &texture, 0, layer as u32, face);
+		}
+
+		let view = texture.create_view(&wgpu::TextureViewDescriptor {
+			label: Some(label),
+			dimension: Some(wgpu::TextureViewDimension::Cube),
+			..Default::default()
+		});
+
+		Self { texture, view }
+	}
+
+	fn write_layer(queue: &wgpu::Queue, texture: &wgpu::Texture, mip_level: u32, layer: u32, image: &RgbaImage) {
+		queue.write_texture(
+			wgpu::TexelCopyTextureInfo {
+				texture,
+				mip_level,
+				origin: wgpu::Origin3d { x: 0, y: 0, z: layer },
+				aspect: wgpu::TextureAspect::All,
+			},
+			image.as_raw(),
+			wgpu::TexelCopyBufferLayout {
+				offset: 0,
+				bytes_per_row: Some(4 * image.width()),
+				rows_per_image: Some(image.height()),
+			},
+			wgpu::Extent3d {
+				width: image.width(),
+				height: image.height(),
+				depth_or_array_layers: 1,
+			},
+		);
+	}
+}